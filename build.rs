@@ -12,6 +12,11 @@ compile_error! {
     "`backtrace` feature without `std` feature is not supported"
 }
 
+#[cfg(all(feature = "color", not(feature = "std")))]
+compile_error! {
+    "`color` feature without `std` feature is not supported"
+}
+
 // This code exercises the surface area that we expect of the std Backtrace
 // type. If the current toolchain is able to compile it, we go ahead and use
 // backtrace in anyhow.
@@ -58,18 +63,47 @@ const PROBE: &str = r#"
 "#;
 
 fn main() {
+    let rustc = rustc_minor_version();
+
+    if rustc.map_or(false, |rustc| rustc >= 80) {
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_fmt_arguments_as_str)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_is_terminal)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_let_else)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_ptr_addr_of)");
+        println!("cargo:rustc-check-cfg=cfg(anyhow_no_track_caller)");
+        println!("cargo:rustc-check-cfg=cfg(backtrace)");
+        println!("cargo:rustc-check-cfg=cfg(doc_cfg)");
+        println!("cargo:rustc-check-cfg=cfg(std_backtrace)");
+    }
+
+    let mut provider_backtrace = false;
     if cfg!(feature = "std") {
         match compile_probe() {
-            Some(status) if status.success() => println!("cargo:rustc-cfg=backtrace"),
+            Some(status) if status.success() => {
+                println!("cargo:rustc-cfg=backtrace");
+                println!("cargo:rustc-cfg=std_backtrace");
+                provider_backtrace = true;
+            }
             _ => {}
         }
     }
 
-    let rustc = match rustc_minor_version() {
+    let rustc = match rustc {
         Some(rustc) => rustc,
         None => return,
     };
 
+    // std::backtrace::Backtrace is stable since 1.65. Without the nightly
+    // provider API, it is used unless the "backtrace" feature asks for the
+    // backtrace crate, which also reports instruction pointers.
+    if cfg!(feature = "std") && !cfg!(feature = "backtrace") && !provider_backtrace && rustc >= 65 {
+        println!("cargo:rustc-cfg=std_backtrace");
+    }
+
+    if rustc < 46 {
+        println!("cargo:rustc-cfg=anyhow_no_track_caller");
+    }
+
     if rustc < 51 {
         println!("cargo:rustc-cfg=anyhow_no_ptr_addr_of");
     }
@@ -77,6 +111,14 @@ fn main() {
     if rustc < 52 {
         println!("cargo:rustc-cfg=anyhow_no_fmt_arguments_as_str");
     }
+
+    if rustc < 65 {
+        println!("cargo:rustc-cfg=anyhow_no_let_else");
+    }
+
+    if rustc < 70 {
+        println!("cargo:rustc-cfg=anyhow_no_is_terminal");
+    }
 }
 
 fn compile_probe() -> Option<ExitStatus> {
@@ -0,0 +1,171 @@
+use crate::{Error, Errors};
+use alloc::vec::{self, Vec};
+use core::fmt::{self, Debug, Display};
+use core::iter::FromIterator;
+use core::slice;
+
+impl Errors {
+    /// Create an empty collection of errors.
+    pub fn new() -> Self {
+        Errors { errors: Vec::new() }
+    }
+
+    /// Add an error to the collection.
+    pub fn push<E>(&mut self, error: E)
+    where
+        Error: From<E>,
+    {
+        self.errors.push(Error::from(error));
+    }
+
+    /// The number of errors in the collection.
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    /// Returns true if no errors have been collected.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// An iterator over the errors, in the order they were added.
+    pub fn iter(&self) -> slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    /// `Ok(())` if no errors were collected, otherwise all of the errors
+    /// combined into one `anyhow::Error`.
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::from_errors(self, backtrace!()))
+        }
+    }
+
+    /// `Ok(())` if `errors` is empty, otherwise all of the errors combined
+    /// into one `anyhow::Error`.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Errors};
+    ///
+    /// assert!(Errors::combine(Vec::<anyhow::Error>::new()).is_ok());
+    ///
+    /// let error = Errors::combine(vec![anyhow!("a"), anyhow!("b")]).unwrap_err();
+    /// assert_eq!(error.to_string(), "2 errors occurred");
+    /// ```
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn combine<I, E>(errors: I) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = E>,
+        Error: From<E>,
+    {
+        Errors {
+            errors: errors.into_iter().map(Error::from).collect(),
+        }
+        .into_result()
+    }
+
+    /// Collect an iterator of `Result`s, gathering every error rather than
+    /// stopping at the first one.
+    ///
+    /// If every item is `Ok`, the values are collected into `C`. Otherwise
+    /// the values are discarded and every error is returned together as one
+    /// `anyhow::Error`.
+    ///
+    /// ```
+    /// use anyhow::Errors;
+    ///
+    /// let inputs = ["1", "x", "3", "y"];
+    /// let error = Errors::collect::<Vec<u8>, _, _, _>(inputs.iter().map(|s| s.parse::<u8>()))
+    ///     .unwrap_err();
+    /// assert_eq!(error.downcast_ref::<Errors>().unwrap().len(), 2);
+    /// ```
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn collect<C, I, T, E>(iter: I) -> Result<C, Error>
+    where
+        C: FromIterator<T>,
+        I: IntoIterator<Item = Result<T, E>>,
+        Error: From<E>,
+    {
+        let mut values = Vec::new();
+        let mut errors = Errors::new();
+        for result in iter {
+            match result {
+                Ok(value) => values.push(value),
+                Err(error) => errors.push(error),
+            }
+        }
+        errors.into_result()?;
+        Ok(values.into_iter().collect())
+    }
+}
+
+impl Default for Errors {
+    fn default() -> Self {
+        Errors::new()
+    }
+}
+
+impl From<Errors> for Error {
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(errors: Errors) -> Self {
+        Error::from_errors(errors, backtrace!())
+    }
+}
+
+impl FromIterator<Error> for Errors {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Error>,
+    {
+        Errors {
+            errors: Vec::from_iter(iter),
+        }
+    }
+}
+
+impl Extend<Error> for Errors {
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = Error>,
+    {
+        self.errors.extend(iter);
+    }
+}
+
+impl IntoIterator for Errors {
+    type Item = Error;
+    type IntoIter = vec::IntoIter<Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Errors {
+    type Item = &'a Error;
+    type IntoIter = slice::Iter<'a, Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
+
+impl Display for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.errors.len() {
+            1 => f.write_str("1 error occurred"),
+            n => write!(f, "{} errors occurred", n),
+        }
+    }
+}
+
+impl Debug for Errors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(&self.errors).finish()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::error::ErrorImpl;
+use crate::ptr::Ref;
+use alloc::boxed::Box;
+use core::any::Any;
+use core::fmt::{self, Display};
+use core::marker::PhantomData;
+use core::slice;
+
+pub(crate) use crate::Attachments;
+
+pub(crate) struct Attachment {
+    value: Box<dyn Any + Send + Sync>,
+    display: Option<fn(&(dyn Any + Send + Sync), &mut fmt::Formatter) -> fmt::Result>,
+}
+
+impl Attachment {
+    pub fn new<T>(value: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        Attachment {
+            value: Box::new(value),
+            display: None,
+        }
+    }
+
+    pub fn printable<T>(value: T) -> Self
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        Attachment {
+            value: Box::new(value),
+            display: Some(display_attachment::<T>),
+        }
+    }
+
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.value.downcast_ref::<T>()
+    }
+
+    pub fn is_printable(&self) -> bool {
+        self.display.is_some()
+    }
+}
+
+impl Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.display {
+            Some(display) => display(&*self.value, f),
+            None => Ok(()),
+        }
+    }
+}
+
+fn display_attachment<T>(value: &(dyn Any + Send + Sync), f: &mut fmt::Formatter) -> fmt::Result
+where
+    T: Display + 'static,
+{
+    // The attachment was constructed by Attachment::printable::<T> so this
+    // downcast cannot fail.
+    Display::fmt(value.downcast_ref::<T>().unwrap(), f)
+}
+
+// Walks every attachment on every layer of an anyhow::Error, outermost layer
+// first and most recently attached first within each layer.
+#[derive(Clone)]
+pub(crate) struct AttachmentsState<'a> {
+    next_layer: Option<Ref<'a, ErrorImpl>>,
+    current: slice::Iter<'a, Attachment>,
+}
+
+impl<'a> AttachmentsState<'a> {
+    pub fn new(head: Ref<'a, ErrorImpl>) -> Self {
+        AttachmentsState {
+            next_layer: Some(head),
+            current: [].iter(),
+        }
+    }
+}
+
+impl<'a> Iterator for AttachmentsState<'a> {
+    type Item = &'a Attachment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(attachment) = self.current.next_back() {
+                return Some(attachment);
+            }
+            let layer = self.next_layer?;
+            unsafe {
+                self.current = ErrorImpl::attachments(layer).iter();
+                self.next_layer = ErrorImpl::inner(layer);
+            }
+        }
+    }
+}
+
+impl<'a, T> Attachments<'a, T> {
+    pub(crate) fn new(head: Ref<'a, ErrorImpl>) -> Self {
+        Attachments {
+            state: AttachmentsState::new(head),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T> Iterator for Attachments<'a, T>
+where
+    T: 'static,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.state
+            .by_ref()
+            .find_map(|attachment| attachment.downcast_ref::<T>())
+    }
+}
+
+impl<T> Clone for Attachments<'_, T> {
+    fn clone(&self) -> Self {
+        Attachments {
+            state: self.state.clone(),
+            marker: PhantomData,
+        }
+    }
+}
@@ -1,35 +1,35 @@
 
-#[cfg(backtrace)]
+#[cfg(std_backtrace)]
 pub(crate) use std::backtrace::{Backtrace, BacktraceStatus};
 
-#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[cfg(all(not(std_backtrace), feature = "backtrace"))]
 pub(crate) use self::capture::{Backtrace, BacktraceStatus};
 
-#[cfg(not(any(backtrace, feature = "backtrace")))]
+#[cfg(not(any(std_backtrace, feature = "backtrace")))]
 pub(crate) enum Backtrace {}
 
-#[cfg(backtrace)]
+#[cfg(std_backtrace)]
 macro_rules! impl_backtrace {
     () => {
         std::backtrace::Backtrace
     };
 }
 
-#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[cfg(all(not(std_backtrace), feature = "backtrace"))]
 macro_rules! impl_backtrace {
     () => {
         impl core::fmt::Debug + core::fmt::Display
     };
 }
 
-#[cfg(any(backtrace, feature = "backtrace"))]
+#[cfg(any(std_backtrace, feature = "backtrace"))]
 macro_rules! backtrace {
     () => {
-        Some(crate::backtrace::Backtrace::capture())
+        Some(crate::backtrace::capture())
     };
 }
 
-#[cfg(not(any(backtrace, feature = "backtrace")))]
+#[cfg(not(any(std_backtrace, feature = "backtrace")))]
 macro_rules! backtrace {
     () => {
         None
@@ -46,21 +46,21 @@ macro_rules! backtrace_if_absent {
     };
 }
 
-#[cfg(all(feature = "std", not(backtrace), feature = "backtrace"))]
+#[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
 macro_rules! backtrace_if_absent {
     ($err:expr) => {
         backtrace!()
     };
 }
 
-#[cfg(all(feature = "std", not(backtrace), not(feature = "backtrace")))]
+#[cfg(all(feature = "std", not(any(std_backtrace, feature = "backtrace"))))]
 macro_rules! backtrace_if_absent {
     ($err:expr) => {
         None
     };
 }
 
-#[cfg(all(not(backtrace), feature = "backtrace"))]
+#[cfg(all(not(std_backtrace), feature = "backtrace"))]
 mod capture {
     use backtrace::{BacktraceFmt, BytesOrWideString, Frame, PrintFmt, SymbolName};
     use core::cell::UnsafeCell;
@@ -166,14 +166,20 @@ mod capture {
         }
     }
 
+    impl BytesOrWide {
+        fn as_bows(&self) -> BytesOrWideString<'_> {
+            match self {
+                BytesOrWide::Bytes(w) => BytesOrWideString::Bytes(w),
+                BytesOrWide::Wide(w) => BytesOrWideString::Wide(w),
+            }
+        }
+    }
+
     impl Debug for BytesOrWide {
         fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
             output_filename(
                 fmt,
-                match self {
-                    BytesOrWide::Bytes(w) => BytesOrWideString::Bytes(w),
-                    BytesOrWide::Wide(w) => BytesOrWideString::Wide(w),
-                },
+                self.as_bows(),
                 PrintFmt::Short,
                 env::current_dir().as_ref().ok(),
             )
@@ -201,8 +207,13 @@ mod capture {
 
         #[inline(never)] // want to make sure there's a frame here to remove
         pub(crate) fn capture() -> Backtrace {
-            if Backtrace::enabled() {
-                Backtrace::create(Backtrace::capture as usize)
+            let enabled = match super::policy::decide() {
+                super::policy::Decision::Env => Backtrace::enabled(),
+                super::policy::Decision::Capture => true,
+                super::policy::Decision::Skip => false,
+            };
+            if enabled {
+                Backtrace::create(Backtrace::capture as *const () as usize)
             } else {
                 let inner = Inner::Disabled;
                 Backtrace { inner }
@@ -248,6 +259,65 @@ mod capture {
                 Inner::Captured(_) => BacktraceStatus::Captured,
             }
         }
+
+        // The unresolved instruction pointers of the same frames as `frames`.
+        #[cfg(feature = "symbolize")]
+        pub(crate) fn ips(&self) -> Vec<usize> {
+            match &self.inner {
+                Inner::Unsupported | Inner::Disabled => Vec::new(),
+                Inner::Captured(c) => c.ips.clone(),
+            }
+        }
+
+        // The resolved frames of the backtrace, one per symbol, in the same
+        // order and with the same filtering as the Debug representation.
+        pub(crate) fn frames(&self) -> Vec<crate::BacktraceFrame> {
+            let capture = match &self.inner {
+                Inner::Unsupported | Inner::Disabled => return Vec::new(),
+                Inner::Captured(c) => c.force(),
+            };
+
+            let cwd = env::current_dir();
+            let mut frames = Vec::new();
+            for frame in &capture.frames[capture.actual_start..] {
+                let ip = frame.frame.ip();
+                if ip.is_null() {
+                    continue;
+                }
+
+                if frame.symbols.is_empty() {
+                    frames.push(crate::BacktraceFrame {
+                        ip: Some(ip as usize),
+                        name: None,
+                        file: None,
+                        line: None,
+                        column: None,
+                        inlined: false,
+                    });
+                }
+
+                // Symbols of functions that were inlined come before the
+                // symbol of the function that the frame physically belongs to.
+                let last = frame.symbols.len().saturating_sub(1);
+                for (i, symbol) in frame.symbols.iter().enumerate() {
+                    frames.push(crate::BacktraceFrame {
+                        ip: Some(ip as usize),
+                        name: symbol
+                            .name
+                            .as_ref()
+                            .map(|b| format!("{:#}", SymbolName::new(b))),
+                        file: symbol
+                            .filename
+                            .as_ref()
+                            .map(|b| relative_path(to_path(b.as_bows()), cwd.as_ref().ok())),
+                        line: symbol.lineno,
+                        column: symbol.colno,
+                        inlined: i < last,
+                    });
+                }
+            }
+            frames
+        }
     }
 
     impl Display for Backtrace {
@@ -285,10 +355,7 @@ mod capture {
                         f.print_raw_with_column(
                             frame.frame.ip(),
                             symbol.name.as_ref().map(|b| SymbolName::new(b)),
-                            symbol.filename.as_ref().map(|b| match b {
-                                BytesOrWide::Bytes(w) => BytesOrWideString::Bytes(w),
-                                BytesOrWide::Wide(w) => BytesOrWideString::Wide(w),
-                            }),
+                            symbol.filename.as_ref().map(BytesOrWide::as_bows),
                             symbol.lineno,
                             symbol.colno,
                         )?;
@@ -303,13 +370,24 @@ mod capture {
     struct LazilyResolvedCapture {
         sync: Once,
         capture: UnsafeCell<Capture>,
+        // Copied out of `capture` so that they can be read without resolving.
+        #[cfg(feature = "symbolize")]
+        ips: Vec<usize>,
     }
 
     impl LazilyResolvedCapture {
         fn new(capture: Capture) -> Self {
+            #[cfg(feature = "symbolize")]
+            let ips = capture.frames[capture.actual_start..]
+                .iter()
+                .map(|frame| frame.frame.ip() as usize)
+                .filter(|&ip| ip != 0)
+                .collect();
             LazilyResolvedCapture {
                 sync: Once::new(),
                 capture: UnsafeCell::new(capture),
+                #[cfg(feature = "symbolize")]
+                ips,
             }
         }
 
@@ -358,14 +436,8 @@ mod capture {
         }
     }
 
-    // Prints the filename of the backtrace frame.
-    fn output_filename(
-        fmt: &mut fmt::Formatter,
-        bows: BytesOrWideString,
-        print_fmt: PrintFmt,
-        cwd: Option<&PathBuf>,
-    ) -> fmt::Result {
-        let file: Cow<Path> = match bows {
+    fn to_path(bows: BytesOrWideString) -> Cow<Path> {
+        match bows {
             #[cfg(unix)]
             BytesOrWideString::Bytes(bytes) => {
                 use std::os::unix::ffi::OsStrExt;
@@ -382,10 +454,32 @@ mod capture {
             }
             #[cfg(not(windows))]
             BytesOrWideString::Wide(_wide) => Path::new("<unknown>").into(),
-        };
+        }
+    }
+
+    // The same path that output_filename prints in the short format.
+    fn relative_path(file: Cow<Path>, cwd: Option<&PathBuf>) -> PathBuf {
+        if file.is_absolute() {
+            if let Some(cwd) = cwd {
+                if let Ok(stripped) = file.strip_prefix(cwd) {
+                    return Path::new(".").join(stripped);
+                }
+            }
+        }
+        file.into_owned()
+    }
+
+    // Prints the filename of the backtrace frame.
+    fn output_filename(
+        fmt: &mut fmt::Formatter,
+        bows: BytesOrWideString,
+        print_fmt: PrintFmt,
+        cwd: Option<&PathBuf>,
+    ) -> fmt::Result {
+        let file = to_path(bows);
         if print_fmt == PrintFmt::Short && file.is_absolute() {
             if let Some(cwd) = cwd {
-                if let Ok(stripped) = file.strip_prefix(&cwd) {
+                if let Ok(stripped) = file.strip_prefix(cwd) {
                     if let Some(s) = stripped.to_str() {
                         return write!(fmt, ".{}{}", path::MAIN_SEPARATOR, s);
                     }
@@ -396,6 +490,366 @@ mod capture {
     }
 }
 
+#[cfg(std_backtrace)]
+pub(crate) fn capture() -> Backtrace {
+    match policy::decide() {
+        policy::Decision::Env => Backtrace::capture(),
+        policy::Decision::Capture => Backtrace::force_capture(),
+        policy::Decision::Skip => Backtrace::disabled(),
+    }
+}
+
+#[cfg(all(not(std_backtrace), feature = "backtrace"))]
+pub(crate) fn capture() -> Backtrace {
+    Backtrace::capture()
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+pub(crate) mod policy {
+    use crate::BacktracePolicy;
+    use core::cell::Cell;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    pub(crate) enum Decision {
+        // Up to RUST_LIB_BACKTRACE and RUST_BACKTRACE.
+        Env,
+        Capture,
+        Skip,
+    }
+
+    // 0 is Env, 1 is Never, 2 is Always, and 3 is Sampled(SAMPLING_RATE).
+    // The rate is kept apart so that every u32 fits on 32-bit targets.
+    static GLOBAL: AtomicUsize = AtomicUsize::new(0);
+    static SAMPLING_RATE: AtomicUsize = AtomicUsize::new(0);
+
+    // Shared by every thread and every sampling rate, so that "1 in n" holds
+    // across the whole process.
+    static SAMPLES: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static SCOPED: Cell<Option<BacktracePolicy>> = Cell::new(None);
+    }
+
+    pub(crate) fn set(policy: BacktracePolicy) {
+        let encoded = match policy {
+            BacktracePolicy::Env => 0,
+            BacktracePolicy::Never => 1,
+            BacktracePolicy::Always => 2,
+            BacktracePolicy::Sampled(n) => {
+                SAMPLING_RATE.store(n as usize, Ordering::Relaxed);
+                3
+            }
+        };
+        // Release the rate to whoever sees the tag.
+        GLOBAL.store(encoded, Ordering::Release);
+    }
+
+    fn global() -> BacktracePolicy {
+        match GLOBAL.load(Ordering::Acquire) {
+            0 => BacktracePolicy::Env,
+            1 => BacktracePolicy::Never,
+            2 => BacktracePolicy::Always,
+            _ => BacktracePolicy::Sampled(SAMPLING_RATE.load(Ordering::Relaxed) as u32),
+        }
+    }
+
+    pub(crate) fn scoped<R>(policy: BacktracePolicy, f: impl FnOnce() -> R) -> R {
+        // Restores the enclosing policy even if `f` panics.
+        struct Restore(Option<BacktracePolicy>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                SCOPED.with(|scoped| scoped.set(self.0));
+            }
+        }
+
+        let _restore = Restore(SCOPED.with(|scoped| scoped.replace(Some(policy))));
+        f()
+    }
+
+    pub(crate) fn decide() -> Decision {
+        // The thread-local is inaccessible while the thread is being torn
+        // down, in which case only the global policy applies.
+        let scoped = SCOPED.try_with(Cell::get).unwrap_or(None);
+        match scoped.unwrap_or_else(global) {
+            BacktracePolicy::Env => Decision::Env,
+            BacktracePolicy::Always => Decision::Capture,
+            BacktracePolicy::Never | BacktracePolicy::Sampled(0) => Decision::Skip,
+            BacktracePolicy::Sampled(n) => {
+                if SAMPLES.fetch_add(1, Ordering::Relaxed) % n as usize == 0 {
+                    Decision::Capture
+                } else {
+                    Decision::Skip
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(not(std_backtrace), feature = "backtrace"))]
+pub(crate) fn frames(backtrace: &Backtrace) -> Vec<crate::BacktraceFrame> {
+    backtrace.frames()
+}
+
+#[cfg(all(not(std_backtrace), feature = "symbolize"))]
+pub(crate) fn ips(backtrace: &Backtrace) -> Vec<usize> {
+    backtrace.ips()
+}
+
+// Instruction pointers are not exposed by the standard library.
+#[cfg(all(std_backtrace, feature = "symbolize"))]
+pub(crate) fn ips(backtrace: &Backtrace) -> Vec<usize> {
+    let _ = backtrace;
+    Vec::new()
+}
+
+// The standard library does not give access to the frames of a backtrace, so
+// they are recovered from its rendering, which does not include instruction
+// pointers.
+#[cfg(std_backtrace)]
+pub(crate) fn frames(backtrace: &Backtrace) -> Vec<crate::BacktraceFrame> {
+    match backtrace.status() {
+        BacktraceStatus::Captured => text::parse(&backtrace.to_string()),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+impl crate::BacktraceFrame {
+    /// The instruction pointer of the frame.
+    ///
+    /// This is only known when the backtrace was captured by the "backtrace"
+    /// feature's implementation. The standard library does not report it.
+    pub fn ip(&self) -> Option<usize> {
+        self.ip
+    }
+
+    /// The demangled name of the function, without its hash.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The source file, relative to the current directory where possible.
+    pub fn file(&self) -> Option<&std::path::Path> {
+        self.file.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The 1-based line number within `file`.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// The 1-based column number within `line`.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    /// Whether this function was inlined into the one that follows it in the
+    /// backtrace, rather than having a stack frame of its own.
+    pub fn is_inlined(&self) -> bool {
+        self.inlined
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+impl crate::BacktraceFrames {
+    pub(crate) fn new(backtrace: &Backtrace) -> Self {
+        crate::BacktraceFrames {
+            frames: frames(backtrace).into_iter(),
+        }
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+impl Iterator for crate::BacktraceFrames {
+    type Item = crate::BacktraceFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.frames.size_hint()
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+impl DoubleEndedIterator for crate::BacktraceFrames {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.frames.next_back()
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+impl ExactSizeIterator for crate::BacktraceFrames {}
+
+// Reading of the rendered form of a backtrace, which is the same for the
+// standard library's backtrace and the one implemented in this module:
+//
+//    0: first::function
+//              at ./src/first.rs:10:5
+//       function::inlined::into::first
+//              at ./src/second.rs:20:9
+//    1: second::function
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+pub(crate) mod text {
+    #[cfg(std_backtrace)]
+    use std::path::PathBuf;
+
+    pub(crate) fn frame_number(line: &str) -> Option<usize> {
+        let line = line.trim_start();
+        let colon = line.find(": ")?;
+        line[..colon].parse().ok()
+    }
+
+    pub(crate) fn symbol_name(line: &str) -> &str {
+        let line = line.trim_start();
+        match line.find(": ") {
+            Some(colon) if line[..colon].bytes().all(|b| b.is_ascii_digit()) => &line[colon + 2..],
+            _ => line,
+        }
+    }
+
+    pub(crate) fn location_file(line: &str) -> Option<&str> {
+        let line = line.trim_start();
+        if line.starts_with("at ") {
+            Some(&line["at ".len()..])
+        } else {
+            None
+        }
+    }
+
+    #[cfg(std_backtrace)]
+    pub(crate) fn parse(backtrace: &str) -> Vec<crate::BacktraceFrame> {
+        let mut frames: Vec<crate::BacktraceFrame> = Vec::new();
+        for line in backtrace.lines() {
+            if let Some(location) = location_file(line) {
+                if let Some(frame) = frames.last_mut() {
+                    let (file, line, column) = split_location(location);
+                    frame.file = Some(PathBuf::from(file));
+                    frame.line = line;
+                    frame.column = column;
+                }
+                continue;
+            }
+
+            let numbered = frame_number(line).is_some();
+            if !numbered {
+                match frames.last_mut() {
+                    // The previous symbol was inlined into this one.
+                    Some(previous) => previous.inlined = true,
+                    // Header preceding the first frame.
+                    None => continue,
+                }
+            }
+
+            let name = symbol_name(line);
+            frames.push(crate::BacktraceFrame {
+                ip: None,
+                name: if name == "<unknown>" {
+                    None
+                } else {
+                    Some(name.to_owned())
+                },
+                file: None,
+                line: None,
+                column: None,
+                inlined: false,
+            });
+        }
+        frames
+    }
+
+    // Splits "file:line:column", where the file may itself contain colons.
+    #[cfg(std_backtrace)]
+    fn split_location(location: &str) -> (&str, Option<u32>, Option<u32>) {
+        fn split_number(s: &str) -> Option<(&str, u32)> {
+            let colon = s.rfind(':')?;
+            let number = s[colon + 1..].parse().ok()?;
+            Some((&s[..colon], number))
+        }
+
+        match split_number(location) {
+            Some((rest, last)) => match split_number(rest) {
+                Some((file, line)) => (file, Some(line), Some(last)),
+                None => (rest, Some(last), None),
+            },
+            None => (location, None, None),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) mod filter {
+    use alloc::boxed::Box;
+    use core::ptr;
+    use core::sync::atomic::{AtomicPtr, Ordering};
+
+    type Filter = dyn Fn(&str, Option<&str>) -> bool + Send + Sync;
+
+    // Registered filters form a singly linked list which is only ever pushed
+    // onto, so it can be read without locking. Nodes are never freed.
+    struct Node {
+        // Only consulted when backtraces are supported.
+        #[cfg_attr(not(any(std_backtrace, feature = "backtrace")), allow(dead_code))]
+        filter: Box<Filter>,
+        next: *const Node,
+    }
+
+    static FILTERS: AtomicPtr<Node> = AtomicPtr::new(ptr::null_mut());
+
+    pub(crate) fn add(filter: Box<Filter>) {
+        let node = Box::into_raw(Box::new(Node {
+            filter,
+            next: ptr::null(),
+        }));
+        let mut head = FILTERS.load(Ordering::Acquire);
+        loop {
+            unsafe { (*node).next = head };
+            match FILTERS.compare_exchange_weak(head, node, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => return,
+                Err(current) => head = current,
+            }
+        }
+    }
+
+    // Frames of anyhow's own construction path and of the backtrace capture
+    // machinery, which sit on top of every backtrace that anyhow captures.
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    pub(crate) fn is_capture(function: &str) -> bool {
+        const PREFIXES: &[&str] = &[
+            "anyhow::",
+            "<anyhow::",
+            "backtrace::",
+            "<backtrace::",
+            "std::backtrace",
+            "<std::backtrace",
+        ];
+        PREFIXES.iter().any(|prefix| function.starts_with(prefix))
+            || function.contains(" as anyhow::")
+    }
+
+    // Frames of the runtime that calls main, or of the thread spawning code
+    // that calls the closure of a new thread. Every frame from here down is
+    // uninteresting.
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    pub(crate) fn is_runtime(function: &str) -> bool {
+        function.contains("lang_start") || function.contains("__rust_begin_short_backtrace")
+    }
+
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    pub(crate) fn is_hidden(function: &str, file: Option<&str>) -> bool {
+        let mut node = FILTERS.load(Ordering::Acquire) as *const Node;
+        while let Some(current) = unsafe { node.as_ref() } {
+            if (current.filter)(function, file) {
+                return true;
+            }
+            node = current.next;
+        }
+        false
+    }
+}
+
 fn _assert_send_sync() {
     fn _assert<T: Send + Sync>() {}
     _assert::<Backtrace>();
@@ -0,0 +1,83 @@
+//! Symbolize a backtrace that was exported with `Error::raw_backtrace`.
+//!
+//! ```console
+//! $ anyhow-symbolize [--dump DUMP] DEBUG_FILE...
+//! ```
+//!
+//! The dump is read from standard input unless given with `--dump`. Each
+//! `DEBUG_FILE` is an unstripped copy of one of the binaries of the process
+//! that captured the backtrace, or a separate debug file for it.
+
+use anyhow::{bail, Context, RawBacktrace, Result};
+use std::env;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+const USAGE: &str = "usage: anyhow-symbolize [--dump DUMP] DEBUG_FILE...";
+
+fn main() -> Result<()> {
+    let mut dump_path = None;
+    let mut debug_files = Vec::new();
+    let mut args = env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--dump" {
+            dump_path = Some(PathBuf::from(args.next().context(USAGE)?));
+        } else if arg == "--help" || arg == "-h" {
+            println!("{}", USAGE);
+            return Ok(());
+        } else {
+            debug_files.push(PathBuf::from(arg));
+        }
+    }
+    if debug_files.is_empty() {
+        bail!(USAGE);
+    }
+
+    let dump = match &dump_path {
+        Some(path) => {
+            fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+        }
+        None => {
+            let mut dump = String::new();
+            io::stdin()
+                .read_to_string(&mut dump)
+                .context("failed to read standard input")?;
+            dump
+        }
+    };
+
+    let raw: RawBacktrace = dump.parse()?;
+    let frames = raw.resolve(&debug_files)?;
+
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut number = 0;
+    let mut continuation = false;
+    for frame in &frames {
+        // Functions inlined into a frame are listed before it, under the
+        // same number.
+        if continuation {
+            write!(out, "      ")?;
+        } else {
+            write!(out, "{:>4}: ", number)?;
+            number += 1;
+        }
+        match frame.name() {
+            Some(name) => writeln!(out, "{}", name)?,
+            None => writeln!(out, "{:#x}", frame.ip().unwrap_or(0))?,
+        }
+        if let Some(file) = frame.file() {
+            write!(out, "             at {}", file.display())?;
+            if let Some(line) = frame.line() {
+                write!(out, ":{}", line)?;
+                if let Some(column) = frame.column() {
+                    write!(out, ":{}", column)?;
+                }
+            }
+            writeln!(out)?;
+        }
+        continuation = frame.is_inlined();
+    }
+    Ok(())
+}
@@ -1,6 +1,10 @@
 
 use self::ChainState::*;
+use crate::error::ErrorImpl;
+use crate::ptr::Ref;
 use crate::StdError;
+#[cfg(feature = "std")]
+use core::panic::Location;
 
 #[cfg(feature = "std")]
 use std::vec;
@@ -11,6 +15,8 @@ pub(crate) use crate::Chain;
 #[cfg(not(feature = "std"))]
 pub(crate) struct Chain<'a> {
     state: ChainState<'a>,
+    #[allow(dead_code)]
+    layers: Option<Ref<'a, ErrorImpl>>,
 }
 
 #[derive(Clone)]
@@ -29,8 +35,56 @@ impl<'a> Chain<'a> {
     pub fn new(head: &'a (dyn StdError + 'static)) -> Self {
         Chain {
             state: ChainState::Linked { next: Some(head) },
+            layers: None,
+        }
+    }
+
+    // Chain of an anyhow::Error, which knows the locations of its layers.
+    #[cold]
+    pub(crate) fn with_layers(head: &'a (dyn StdError + 'static), layers: Ref<'a, ErrorImpl>) -> Self {
+        Chain {
+            state: ChainState::Linked { next: Some(head) },
+            layers: Some(layers),
         }
     }
+
+    /// Pair each error in the chain with the source location at which it was
+    /// created or attached as context.
+    ///
+    /// Locations are known only for the layers that were constructed by
+    /// anyhow, such as by `anyhow!`, `Error::new` or `.context(...)`. Lower
+    /// level sources of a `std::error::Error` are paired with `None`.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn f() -> Result<()> {
+    ///     Err(anyhow!("oh no!"))
+    /// }
+    ///
+    /// # fn main() {
+    /// let error = f().context("f failed").unwrap_err();
+    /// for (cause, location) in error.chain().with_locations() {
+    ///     if let Some(location) = location {
+    ///         eprintln!("{} at {}", cause, location);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_locations(
+        self,
+    ) -> impl Iterator<Item = (&'a (dyn StdError + 'static), Option<&'static Location<'static>>)> + 'a
+    {
+        let layers = self.layers;
+        self.map(move |error| {
+            let location = match layers {
+                Some(layers) => unsafe { ErrorImpl::location_of(layers, error) },
+                None => None,
+            };
+            (error, location)
+        })
+    }
 }
 
 impl<'a> Iterator for Chain<'a> {
@@ -98,6 +152,7 @@ impl Default for Chain<'_> {
             state: ChainState::Buffered {
                 rest: Vec::new().into_iter(),
             },
+            layers: None,
         }
     }
 }
\ No newline at end of file
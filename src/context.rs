@@ -7,10 +7,11 @@ use core::fmt::{self, Debug, Display, Write};
 #[cfg(backtrace)]
 use std::any::{Demand, Provider};
 
-mod ext {
+pub(crate) mod ext {
     use super::*;
 
     pub trait StdError {
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static;
@@ -21,6 +22,7 @@ mod ext {
     where
         E: std::error::Error + Send + Sync + 'static,
     {
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static,
@@ -31,6 +33,7 @@ mod ext {
     }
 
     impl StdError for Error {
+        #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
         fn ext_context<C>(self, context: C) -> Error
         where
             C: Display + Send + Sync + 'static,
@@ -44,6 +47,7 @@ impl<T, E> Context<T, E> for Result<T, E>
 where
     E: ext::StdError + Send + Sync + 'static,
 {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -56,6 +60,7 @@ where
         }
     }
 
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -89,6 +94,7 @@ where
 /// }
 /// ```
 impl<T> Context<T, Infallible> for Option<T> {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -101,6 +107,7 @@ impl<T> Context<T, Infallible> for Option<T> {
         }
     }
 
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
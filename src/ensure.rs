@@ -1,15 +1,27 @@
-
-use crate::Error;
-use alloc::string::String;
-use core::fmt::{self, Debug, Write};
-use core::mem::MaybeUninit;
-use core::ptr;
-use core::slice;
-use core::str;
+use crate::{ConditionFailed, Error};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 #[doc(hidden)]
 pub trait BothDebug {
-    fn __dispatch_ensure(self, msg: &'static str) -> Error;
+    fn __dispatch_ensure(
+        self,
+        expression: &'static str,
+        operator: &'static str,
+        file: &'static str,
+        line: u32,
+        verbose: bool,
+    ) -> Error;
+
+    fn __dispatch_clause(
+        self,
+        clauses: &mut Clauses,
+        expression: &'static str,
+        operator: &'static str,
+    );
 }
 
 impl<A, B> BothDebug for (A, B)
@@ -17,97 +29,510 @@ where
     A: Debug,
     B: Debug,
 {
-    fn __dispatch_ensure(self, msg: &'static str) -> Error {
-        render(msg, &self.0, &self.1)
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn __dispatch_ensure(
+        self,
+        expression: &'static str,
+        operator: &'static str,
+        file: &'static str,
+        line: u32,
+        verbose: bool,
+    ) -> Error {
+        ConditionFailed::new(
+            expression,
+            Some(operator),
+            Some(&self.0),
+            Some(&self.1),
+            verbose,
+            file,
+            line,
+        )
+        .into_error()
+    }
+
+    fn __dispatch_clause(
+        self,
+        clauses: &mut Clauses,
+        expression: &'static str,
+        operator: &'static str,
+    ) {
+        clauses.push(expression, Some(operator), Some(&self.0), Some(&self.1));
     }
 }
 
 #[doc(hidden)]
 pub trait NotBothDebug {
-    fn __dispatch_ensure(self, msg: &'static str) -> Error;
+    fn __dispatch_ensure(
+        self,
+        expression: &'static str,
+        operator: &'static str,
+        file: &'static str,
+        line: u32,
+        verbose: bool,
+    ) -> Error;
+
+    fn __dispatch_clause(
+        self,
+        clauses: &mut Clauses,
+        expression: &'static str,
+        operator: &'static str,
+    );
 }
 
 impl<A, B> NotBothDebug for &(A, B) {
-    fn __dispatch_ensure(self, msg: &'static str) -> Error {
-        Error::msg(msg)
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn __dispatch_ensure(
+        self,
+        expression: &'static str,
+        operator: &'static str,
+        file: &'static str,
+        line: u32,
+        verbose: bool,
+    ) -> Error {
+        ConditionFailed::new(expression, Some(operator), None, None, verbose, file, line)
+            .into_error()
+    }
+
+    fn __dispatch_clause(
+        self,
+        clauses: &mut Clauses,
+        expression: &'static str,
+        operator: &'static str,
+    ) {
+        clauses.push(expression, Some(operator), None, None);
     }
 }
 
-struct Buf {
-    bytes: [MaybeUninit<u8>; 40],
-    written: usize,
+// The value of a `matches!` condition that did not match.
+#[doc(hidden)]
+pub trait IsDebug {
+    fn __dispatch_matches(self, clauses: &mut Clauses, expression: &'static str);
 }
 
-impl Buf {
-    fn new() -> Self {
-        Buf {
-            bytes: [MaybeUninit::uninit(); 40],
-            written: 0,
+impl<T> IsDebug for (T,)
+where
+    T: Debug,
+{
+    fn __dispatch_matches(self, clauses: &mut Clauses, expression: &'static str) {
+        clauses.push(expression, None, Some(&self.0), None);
+    }
+}
+
+#[doc(hidden)]
+pub trait NotDebug {
+    fn __dispatch_matches(self, clauses: &mut Clauses, expression: &'static str);
+}
+
+impl<T> NotDebug for &(T,) {
+    fn __dispatch_matches(self, clauses: &mut Clauses, expression: &'static str) {
+        clauses.push(expression, None, None, None);
+    }
+}
+
+// A condition that is not a comparison, or not one that __parse_ensure
+// understands.
+#[doc(hidden)]
+#[cold]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn condition_failed(expression: &'static str, file: &'static str, line: u32) -> Error {
+    ConditionFailed::new(expression, None, None, None, false, file, line).into_error()
+}
+
+// The clauses of a condition that evaluated to false, in evaluation order.
+// For `a && b || c && d` these are the first false clause on each side of the
+// `||`.
+#[doc(hidden)]
+pub struct Clauses {
+    file: &'static str,
+    line: u32,
+    verbose: bool,
+    failed: Vec<ConditionFailed>,
+}
+
+impl Clauses {
+    pub fn new(file: &'static str, line: u32, verbose: bool) -> Self {
+        Clauses {
+            file,
+            line,
+            verbose,
+            failed: Vec::new(),
         }
     }
 
-    fn as_str(&self) -> &str {
-        unsafe {
-            str::from_utf8_unchecked(slice::from_raw_parts(
-                self.bytes.as_ptr().cast::<u8>(),
-                self.written,
-            ))
+    #[cold]
+    fn push(
+        &mut self,
+        expression: &'static str,
+        operator: Option<&'static str>,
+        lhs: Option<&dyn Debug>,
+        rhs: Option<&dyn Debug>,
+    ) {
+        let mut clause = ConditionFailed::new(
+            expression,
+            operator,
+            lhs,
+            rhs,
+            self.verbose,
+            self.file,
+            self.line,
+        );
+        clause.render();
+        self.failed.push(clause);
+    }
+
+    #[cold]
+    pub fn plain(&mut self, expression: &'static str) {
+        self.push(expression, None, None, None);
+    }
+
+    // The whole condition was a single clause.
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn single_error(mut self, expression: &'static str) -> Error {
+        match self.failed.pop() {
+            Some(mut clause) => {
+                clause.expression = expression;
+                clause.into_error()
+            }
+            None => condition_failed(expression, self.file, self.line),
         }
     }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn error(self, expression: &'static str) -> Error {
+        let mut condition = ConditionFailed::new(
+            expression,
+            None,
+            None,
+            None,
+            self.verbose,
+            self.file,
+            self.line,
+        );
+        condition.clauses = self.failed;
+        condition.into_error()
+    }
+}
+
+static OPERAND_LIMIT: AtomicUsize = AtomicUsize::new(1024);
+
+pub(crate) fn set_operand_limit(limit: usize) {
+    OPERAND_LIMIT.store(limit, Ordering::Relaxed);
 }
 
-impl Write for Buf {
-    fn write_str(&mut self, s: &str) -> fmt::Result {
-        if s.bytes().any(|b| b == b' ' || b == b'\n') {
-            return Err(fmt::Error);
+impl ConditionFailed {
+    fn new(
+        expression: &'static str,
+        operator: Option<&'static str>,
+        lhs: Option<&dyn Debug>,
+        rhs: Option<&dyn Debug>,
+        verbose: bool,
+        file: &'static str,
+        line: u32,
+    ) -> Self {
+        let limit = OPERAND_LIMIT.load(Ordering::Relaxed);
+        let pretty = match (lhs, rhs) {
+            (Some(lhs), Some(rhs)) if verbose => Some((
+                format_truncated(format_args!("{:#?}", lhs), limit),
+                format_truncated(format_args!("{:#?}", rhs), limit),
+            )),
+            _ => None,
+        };
+        ConditionFailed {
+            expression,
+            operator,
+            lhs: lhs.map(|lhs| format_truncated(format_args!("{:?}", lhs), limit)),
+            rhs: rhs.map(|rhs| format_truncated(format_args!("{:?}", rhs), limit)),
+            pretty,
+            verbose,
+            clauses: Vec::new(),
+            file,
+            line,
+            message: String::new(),
+        }
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn into_error(mut self) -> Error {
+        self.render();
+        Error::from_condition(self, backtrace!())
+    }
+
+    fn render(&mut self) {
+        struct Message<'a>(&'a ConditionFailed);
+
+        impl Display for Message<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.write_message(f)
+            }
+        }
+
+        self.message = Message(self).to_string();
+    }
+
+    /// The source text of the condition, as written in the `ensure!`.
+    pub fn expression(&self) -> &str {
+        self.expression
+    }
+
+    /// The comparison operator of the condition, such as `==` or `<`.
+    ///
+    /// This is `None` if the condition is not a comparison.
+    pub fn operator(&self) -> Option<&str> {
+        self.operator
+    }
+
+    /// The `Debug` representation of the left-hand side of the comparison.
+    ///
+    /// For a `matches!(value, pattern)` condition, this is the value that did
+    /// not match the pattern.
+    ///
+    /// This is `None` if the condition is neither, or if the operands do not
+    /// implement `Debug`. Like in the message, the value is truncated at the
+    /// limit set by [`set_ensure_operand_limit`][crate::set_ensure_operand_limit].
+    pub fn lhs(&self) -> Option<&str> {
+        self.lhs.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The `Debug` representation of the right-hand side of the comparison.
+    ///
+    /// This is `None` if the condition is not a comparison, or if either
+    /// side does not implement `Debug`. The value is truncated the same way as
+    /// [`lhs`][Self::lhs].
+    pub fn rhs(&self) -> Option<&str> {
+        self.rhs.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The clauses that were false, if the condition is made of several
+    /// clauses joined by `&&` or `||`.
+    ///
+    /// Clauses are evaluated in order and short-circuit like they would in an
+    /// `if`, so for `a && b` only one of the two can be false, while for
+    /// `a || b` both are. Each clause has its own [`operator`][Self::operator]
+    /// and operands.
+    ///
+    /// This is empty for a condition that is not joined by `&&` or `||`.
+    pub fn clauses(&self) -> &[ConditionFailed] {
+        &self.clauses
+    }
+
+    /// The source file of the `ensure!`.
+    pub fn file(&self) -> &str {
+        self.file
+    }
+
+    /// The line of the `ensure!` within `file`.
+    pub fn line(&self) -> u32 {
+        self.line
+    }
+
+    fn write_compact(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Operands are only shown inline if they are short and fit on the line
+        // without ambiguity.
+        match (&self.lhs, &self.rhs) {
+            (Some(lhs), Some(rhs)) if is_compact(lhs) && is_compact(rhs) => {
+                write!(f, " ({} vs {})", lhs, rhs)
+            }
+            (Some(value), None) if is_compact(value) => write!(f, " (found {})", value),
+            _ => Ok(()),
         }
+    }
 
-        let remaining = self.bytes.len() - self.written;
-        if s.len() > remaining {
-            return Err(fmt::Error);
+    // The operands were truncated when the condition failed, at the limit in
+    // effect then.
+    fn write_verbose(&self, f: &mut fmt::Formatter, indent: &str) -> fmt::Result {
+        match (&self.lhs, &self.rhs) {
+            (Some(lhs), Some(rhs)) => {
+                if let Some((lhs, rhs)) = &self.pretty {
+                    if lhs != rhs
+                        && lhs.contains('\n')
+                        && rhs.contains('\n')
+                        && write_diff(f, indent, lhs, rhs)?
+                    {
+                        return Ok(());
+                    }
+                }
+                write!(f, "\n{}  left: {}", indent, lhs)?;
+                write!(f, "\n{} right: {}", indent, rhs)
+            }
+            (Some(value), None) => write!(f, "\n{} found: {}", indent, value),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for ConditionFailed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl ConditionFailed {
+    fn write_message(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Condition failed: `{}`", self.expression)?;
+        if self.clauses.is_empty() {
+            return if self.verbose {
+                self.write_verbose(f, "")
+            } else {
+                self.write_compact(f)
+            };
         }
 
-        unsafe {
-            ptr::copy_nonoverlapping(
-                s.as_ptr(),
-                self.bytes.as_mut_ptr().add(self.written).cast::<u8>(),
-                s.len(),
-            );
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if self.verbose {
+                write!(f, "\n  `{}`", clause.expression)?;
+                clause.write_verbose(f, "  ")?;
+            } else {
+                f.write_str(if i == 0 { ": " } else { ", " })?;
+                write!(f, "`{}`", clause.expression)?;
+                clause.write_compact(f)?;
+            }
         }
-        self.written += s.len();
         Ok(())
     }
 }
 
-fn render(msg: &'static str, lhs: &dyn Debug, rhs: &dyn Debug) -> Error {
-    let mut lhs_buf = Buf::new();
-    if fmt::write(&mut lhs_buf, format_args!("{:?}", lhs)).is_ok() {
-        let mut rhs_buf = Buf::new();
-        if fmt::write(&mut rhs_buf, format_args!("{:?}", rhs)).is_ok() {
-            let lhs_str = lhs_buf.as_str();
-            let rhs_str = rhs_buf.as_str();
-            // "{msg} ({lhs} vs {rhs})"
-            let len = msg.len() + 2 + lhs_str.len() + 4 + rhs_str.len() + 1;
-            let mut string = String::with_capacity(len);
-            string.push_str(msg);
-            string.push_str(" (");
-            string.push_str(lhs_str);
-            string.push_str(" vs ");
-            string.push_str(rhs_str);
-            string.push(')');
-            return Error::msg(string);
+#[cfg(feature = "std")]
+impl std::error::Error for ConditionFailed {}
+
+fn is_compact(operand: &str) -> bool {
+    operand.len() <= 40 && !operand.bytes().any(|b| b == b' ' || b == b'\n')
+}
+
+// Formats a value into a String of at most `limit` bytes, followed by "…" if
+// anything was cut off. Formatting stops at the limit, so that a huge operand
+// costs no more than a short one.
+pub(crate) fn format_truncated(args: fmt::Arguments, limit: usize) -> String {
+    struct Truncated {
+        string: String,
+        limit: usize,
+        truncated: bool,
+    }
+
+    impl Write for Truncated {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            if self.truncated {
+                return Err(fmt::Error);
+            }
+            let remaining = self.limit - self.string.len();
+            if s.len() <= remaining {
+                self.string.push_str(s);
+                return Ok(());
+            }
+            let mut end = remaining;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.string.push_str(&s[..end]);
+            self.truncated = true;
+            Err(fmt::Error)
+        }
+    }
+
+    let mut truncated = Truncated {
+        string: String::new(),
+        limit,
+        truncated: false,
+    };
+    // An error that is not the cut comes from the value's own formatting, and
+    // leaves whatever it wrote before failing.
+    let _ = truncated.write_fmt(args);
+    if truncated.truncated {
+        truncated.string.push('…');
+    }
+    truncated.string
+}
+
+// Writes a line diff of the two operands, based on their longest common
+// subsequence of lines. Returns false without writing anything if they are
+// too long to be compared in reasonable time.
+fn write_diff(
+    f: &mut fmt::Formatter,
+    indent: &str,
+    lhs: &str,
+    rhs: &str,
+) -> Result<bool, fmt::Error> {
+    const MAX_CELLS: usize = 1 << 20;
+
+    let lhs: Vec<&str> = lhs.lines().collect();
+    let rhs: Vec<&str> = rhs.lines().collect();
+    let width = rhs.len() + 1;
+    if (lhs.len() + 1).saturating_mul(width) > MAX_CELLS {
+        return Ok(false);
+    }
+
+    // common[i * width + j] is the length of the longest common subsequence
+    // of lhs[i..] and rhs[j..].
+    let mut common = vec![0u32; (lhs.len() + 1) * width];
+    for i in (0..lhs.len()).rev() {
+        for j in (0..rhs.len()).rev() {
+            common[i * width + j] = if lhs[i] == rhs[j] {
+                common[(i + 1) * width + j + 1] + 1
+            } else {
+                common[(i + 1) * width + j].max(common[i * width + j + 1])
+            };
+        }
+    }
+
+    write!(f, "\n{}  diff (- left, + right):", indent)?;
+    let (mut i, mut j) = (0, 0);
+    while i < lhs.len() || j < rhs.len() {
+        if i < lhs.len() && j < rhs.len() && lhs[i] == rhs[j] {
+            write!(f, "\n{}      {}", indent, rhs[j])?;
+            i += 1;
+            j += 1;
+        } else if j == rhs.len()
+            || i < lhs.len() && common[(i + 1) * width + j] >= common[i * width + j + 1]
+        {
+            write!(f, "\n{}    - {}", indent, lhs[i])?;
+            i += 1;
+        } else {
+            write!(f, "\n{}    + {}", indent, rhs[j])?;
+            j += 1;
         }
     }
-    Error::msg(msg)
+    Ok(true)
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __parse_ensure {
+    (atom () (@clause $failed:ident $not:tt) $fuel:tt {($($rhs:tt)+) ($($lhs:tt)+) $op:tt} $dup:tt) => {
+        $crate::__ensure_clause!(@not $failed $($lhs)+, $op, $($rhs)+)
+    };
+
+    (atom () (kind: $kind:expr, $($bail:tt)*) $fuel:tt {($($rhs:tt)+) ($($lhs:tt)+) $op:tt} $dup:tt $(,)?) => {
+        $crate::__fancy_ensure!(kind: $kind, $($lhs)+, $op, $($rhs)+)
+    };
+
+    (atom () (verbose: $($bail:tt)*) $fuel:tt {($($rhs:tt)+) ($($lhs:tt)+) $op:tt} $dup:tt $(,)?) => {
+        $crate::__fancy_ensure!(verbose: $($lhs)+, $op, $($rhs)+)
+    };
+
     (atom () $bail:tt $fuel:tt {($($rhs:tt)+) ($($lhs:tt)+) $op:tt} $dup:tt $(,)?) => {
         $crate::__fancy_ensure!($($lhs)+, $op, $($rhs)+)
     };
 
+    // clauses joined by && or ||, and single clauses that can be explained
+
+    (atom () $bail:tt $fuel:tt {($($rhs:tt)+) $([$($clause:tt)*] $conn:tt)+ ($($lhs:tt)+) $op:tt} $dup:tt $(,)?) => {
+        $crate::__compound_ensure!($bail $fuel [($($lhs)+ $op $($rhs)+) cmp ($($lhs)+) $op ($($rhs)+)] $([$($clause)*] $conn)+)
+    };
+
+    (atom () $bail:tt $fuel:tt {($($last:tt)+) $([$($clause:tt)*] $conn:tt)+} $dup:tt $(,)?) => {
+        $crate::__compound_ensure!($bail $fuel [($($last)+) expr] $([$($clause)*] $conn)+)
+    };
+
+    (atom () $bail:tt $fuel:tt {(! ($($inner:tt)*))} $dup:tt $(,)?) => {
+        $crate::__compound_ensure!($bail $fuel [(! ($($inner)*)) expr])
+    };
+
+    (atom () $bail:tt $fuel:tt {(matches ! ($($args:tt)*))} $dup:tt $(,)?) => {
+        $crate::__compound_ensure!($bail $fuel [(matches ! ($($args)*)) expr])
+    };
+
     // low precedence control flow constructs
 
     (0 $stack:tt ($($bail:tt)*) $fuel:tt $parse:tt $dup:tt return $($rest:tt)*) => {
@@ -735,6 +1160,22 @@ macro_rules! __parse_ensure {
         $crate::__parse_ensure!(0 ($($stack)*) $bail ($($fuel)*) {($($buf)* $or) $($parse)*} ($($rest)*) $($rest)*)
     };
 
+    (atom () $bail:tt (~$($fuel:tt)*) {($($rhs:tt)+) $([$($clause:tt)*] $conn:tt)* ($($lhs:tt)+) $op:tt} ($and:tt $($dup:tt)*) && $($rest:tt)*) => {
+        $crate::__parse_ensure!(0 () $bail ($($fuel)*) {() $([$($clause)*] $conn)* [($($lhs)+ $op $($rhs)+) cmp ($($lhs)+) $op ($($rhs)+)] $and} ($($rest)*) $($rest)*)
+    };
+
+    (atom () $bail:tt (~$($fuel:tt)*) {($($buf:tt)+) $([$($clause:tt)*] $conn:tt)*} ($and:tt $($dup:tt)*) && $($rest:tt)*) => {
+        $crate::__parse_ensure!(0 () $bail ($($fuel)*) {() $([$($clause)*] $conn)* [($($buf)+) expr] $and} ($($rest)*) $($rest)*)
+    };
+
+    (atom () $bail:tt (~$($fuel:tt)*) {($($rhs:tt)+) $([$($clause:tt)*] $conn:tt)* ($($lhs:tt)+) $op:tt} ($or:tt $($dup:tt)*) || $($rest:tt)*) => {
+        $crate::__parse_ensure!(0 () $bail ($($fuel)*) {() $([$($clause)*] $conn)* [($($lhs)+ $op $($rhs)+) cmp ($($lhs)+) $op ($($rhs)+)] $or} ($($rest)*) $($rest)*)
+    };
+
+    (atom () $bail:tt (~$($fuel:tt)*) {($($buf:tt)+) $([$($clause:tt)*] $conn:tt)*} ($or:tt $($dup:tt)*) || $($rest:tt)*) => {
+        $crate::__parse_ensure!(0 () $bail ($($fuel)*) {() $([$($clause)*] $conn)* [($($buf)+) expr] $or} ($($rest)*) $($rest)*)
+    };
+
     (atom ($($stack:tt)+) $bail:tt (~$($fuel:tt)*) {($($buf:tt)*) $($parse:tt)*} ($assign:tt $($dup:tt)*) = $($rest:tt)*) => {
         $crate::__parse_ensure!(0 ($($stack)*) $bail ($($fuel)*) {($($buf)* $assign) $($parse)*} ($($rest)*) $($rest)*)
     };
@@ -789,36 +1230,248 @@ macro_rules! __parse_ensure {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __fancy_ensure {
+    (kind: $kind:expr, $lhs:expr, $op:tt, $rhs:expr) => {
+        $crate::__fancy_ensure!(@ false ($kind), $lhs, $op, $rhs)
+    };
+    (verbose: $lhs:expr, $op:tt, $rhs:expr) => {
+        $crate::__fancy_ensure!(@ true (), $lhs, $op, $rhs)
+    };
     ($lhs:expr, $op:tt, $rhs:expr) => {
+        $crate::__fancy_ensure!(@ false (), $lhs, $op, $rhs)
+    };
+    (@ $verbose:tt $kinded:tt, $lhs:expr, $op:tt, $rhs:expr) => {
         match (&$lhs, &$rhs) {
             (lhs, rhs) => {
                 if !(lhs $op rhs) {
                     #[allow(unused_imports)]
                     use $crate::__private::{BothDebug, NotBothDebug};
-                    return Err((lhs, rhs).__dispatch_ensure(
+                    return Err($crate::__ensure_error!($kinded (lhs, rhs).__dispatch_ensure(
+                        $crate::__private::concat!(
+                            $crate::__private::stringify!($lhs),
+                            " ",
+                            $crate::__private::stringify!($op),
+                            " ",
+                            $crate::__private::stringify!($rhs),
+                        ),
+                        $crate::__private::stringify!($op),
+                        $crate::__private::file!(),
+                        $crate::__private::line!(),
+                        $verbose,
+                    )));
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __compound_ensure {
+    // A clause of a negated condition, which is not explained any further.
+    ((@clause $failed:ident $not:tt) $fuel:tt $($clauses:tt)*) => {
+        $crate::__ensure_clause!(@plain $failed $not)
+    };
+
+    ((kind: $kind:expr, $($cond:tt)*) $fuel:tt $($clauses:tt)*) => {
+        $crate::__compound_ensure!(@ false ($kind) ($($cond)*) $fuel $($clauses)*)
+    };
+
+    ((verbose: $($cond:tt)*) $fuel:tt $($clauses:tt)*) => {
+        $crate::__compound_ensure!(@ true () ($($cond)*) $fuel $($clauses)*)
+    };
+
+    (($($cond:tt)*) $fuel:tt $($clauses:tt)*) => {
+        $crate::__compound_ensure!(@ false () ($($cond)*) $fuel $($clauses)*)
+    };
+
+    (@ $verbose:tt $kinded:tt ($cond:expr $(,)?) $fuel:tt [$clause:tt $($kind:tt)*]) => {{
+        let mut failed = $crate::__private::Clauses::new(
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $verbose,
+        );
+        if !$crate::__ensure_clause!(failed $fuel $clause $($kind)*) {
+            return $crate::__private::Err($crate::__ensure_error!($kinded failed.single_error(
+                $crate::__private::stringify!($cond),
+            )));
+        }
+    }};
+
+    // The last clause comes first so that it can be told apart from the others.
+    (@ $verbose:tt $kinded:tt ($cond:expr $(,)?) $fuel:tt [$last:tt $($lastkind:tt)*] $([$clause:tt $($kind:tt)*] $conn:tt)+) => {{
+        let mut failed = $crate::__private::Clauses::new(
+            $crate::__private::file!(),
+            $crate::__private::line!(),
+            $verbose,
+        );
+        if !($($crate::__ensure_clause!(failed $fuel $clause $($kind)*) $conn)+ $crate::__ensure_clause!(failed $fuel $last $($lastkind)*)) {
+            return $crate::__private::Err($crate::__ensure_error!($kinded failed.error(
+                $crate::__private::stringify!($cond),
+            )));
+        }
+    }};
+}
+
+// Wraps the error of a failed ensure!(kind: $kind, ...) in Kinded.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_error {
+    (() $error:expr) => {
+        $error
+    };
+    (($kind:expr) $error:expr) => {
+        $crate::Kinded::new($kind, $error)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_clause {
+    ($failed:ident $fuel:tt $cond:tt cmp ($($lhs:tt)+) $op:tt ($($rhs:tt)+)) => {
+        $crate::__ensure_clause!(@cmp $failed $($lhs)+, $op, $($rhs)+)
+    };
+
+    ($failed:ident $fuel:tt (! ($($inner:tt)*)) expr) => {
+        $crate::__parse_ensure!(
+            /* state */ 0
+            /* stack */ ()
+            /* bail */ (@clause $failed (! ($($inner)*)))
+            /* fuel */ $fuel
+            /* parse */ {()}
+            /* dup */ ($($inner)*)
+            /* rest */ $($inner)*
+        )
+    };
+
+    ($failed:ident $fuel:tt (matches ! ($($args:tt)*)) expr) => {
+        $crate::__ensure_clause!(@matches $failed (matches ! ($($args)*)) $($args)*)
+    };
+
+    ($failed:ident $fuel:tt $cond:tt expr) => {
+        $crate::__ensure_clause!(@plain $failed $cond)
+    };
+
+    (@cmp $failed:ident $lhs:expr, $op:tt, $rhs:expr) => {
+        match (&$lhs, &$rhs) {
+            (lhs, rhs) => {
+                let ok = lhs $op rhs;
+                if !ok {
+                    #[allow(unused_imports)]
+                    use $crate::__private::{BothDebug, NotBothDebug};
+                    (lhs, rhs).__dispatch_clause(
+                        &mut $failed,
                         $crate::__private::concat!(
-                            "Condition failed: `",
                             $crate::__private::stringify!($lhs),
                             " ",
                             $crate::__private::stringify!($op),
                             " ",
                             $crate::__private::stringify!($rhs),
-                            "`",
                         ),
-                    ));
+                        $crate::__private::stringify!($op),
+                    );
                 }
+                ok
             }
         }
     };
+
+    (@not $failed:ident $lhs:expr, $op:tt, $rhs:expr) => {
+        match (&$lhs, &$rhs) {
+            (lhs, rhs) => {
+                let ok = !(lhs $op rhs);
+                if !ok {
+                    #[allow(unused_imports)]
+                    use $crate::__private::{BothDebug, NotBothDebug};
+                    (lhs, rhs).__dispatch_clause(
+                        &mut $failed,
+                        $crate::__private::concat!(
+                            "!(",
+                            $crate::__private::stringify!($lhs),
+                            " ",
+                            $crate::__private::stringify!($op),
+                            " ",
+                            $crate::__private::stringify!($rhs),
+                            ")",
+                        ),
+                        $crate::__private::stringify!($op),
+                    );
+                }
+                ok
+            }
+        }
+    };
+
+    (@matches $failed:ident $cond:tt $value:expr, | $($pat:tt)*) => {
+        $crate::__ensure_clause!(@plain $failed $cond)
+    };
+
+    (@matches $failed:ident $cond:tt $value:expr, $($pat:pat)|+ $(if $guard:expr)? $(,)?) => {
+        match $value {
+            $($pat)|+ $(if $guard)? => true,
+            ref value => {
+                #[allow(unused_imports)]
+                use $crate::__private::{IsDebug, NotDebug};
+                (value,).__dispatch_matches(
+                    &mut $failed,
+                    $crate::__private::concat!(
+                        "matches!(",
+                        $crate::__private::stringify!($value),
+                        ", ",
+                        $crate::__private::stringify!($($pat)|+),
+                        $(" if ", $crate::__private::stringify!($guard),)?
+                        ")",
+                    ),
+                );
+                false
+            }
+        }
+    };
+
+    (@matches $failed:ident $cond:tt $($args:tt)*) => {
+        $crate::__ensure_clause!(@plain $failed $cond)
+    };
+
+    (@plain $failed:ident ($cond:expr)) => {{
+        let ok: bool = $cond;
+        if !ok {
+            $failed.plain($crate::__private::stringify!($cond));
+        }
+        ok
+    }};
 }
 
 #[doc(hidden)]
 #[macro_export]
 macro_rules! __fallback_ensure {
+    (@clause $failed:ident $not:tt) => {
+        $crate::__ensure_clause!(@plain $failed $not)
+    };
+    (verbose: $($rest:tt)*) => {
+        $crate::__fallback_ensure!($($rest)*)
+    };
+    (kind: $kind:expr, $cond:expr $(,)?) => {
+        if !$cond {
+            return $crate::__private::Err($crate::Kinded::new(
+                $kind,
+                $crate::__private::condition_failed(
+                    $crate::__private::stringify!($cond),
+                    $crate::__private::file!(),
+                    $crate::__private::line!(),
+                ),
+            ));
+        }
+    };
+    (kind: $kind:expr, $cond:expr, $($msg:tt)+) => {
+        if !$cond {
+            return $crate::__private::Err($crate::Kinded::new($kind, $crate::__anyhow!($($msg)+)));
+        }
+    };
     ($cond:expr $(,)?) => {
         if !$cond {
-            return $crate::__private::Err($crate::Error::msg(
-                $crate::__private::concat!("Condition failed: `", $crate::__private::stringify!($cond), "`")
+            return $crate::__private::Err($crate::__private::condition_failed(
+                $crate::__private::stringify!($cond),
+                $crate::__private::file!(),
+                $crate::__private::line!(),
             ));
         }
     };
@@ -1,15 +1,32 @@
+use crate::attachment::{Attachment, Attachments};
 use crate::backtrace::Backtrace;
 use crate::chain::Chain;
-#[cfg(any(feature = "std", anyhow_no_ptr_addr_of))]
-use crate::ptr::Mut;
-use crate::ptr::{Own, Ref};
-use crate::{Error, StdError};
+use crate::handler;
+use crate::ptr::{Mut, Own, Ref};
+#[cfg(feature = "serde")]
+use crate::remote::RemoteError;
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+use crate::BacktraceFrames;
+#[cfg(feature = "serde")]
+use crate::RemoteBacktrace;
+#[cfg(feature = "symbolize")]
+use crate::RawBacktrace;
+#[cfg(feature = "std")]
+use crate::Redacted;
+#[cfg(feature = "std")]
+use crate::SharedError;
+use crate::{ConditionFailed, Error, Errors, FormattedMessage, ReportHandler, StdError};
 use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
 #[cfg(backtrace)]
 use core::any::Demand;
 use core::any::TypeId;
+#[cfg(any(feature = "std", feature = "serde"))]
+use core::any;
 use core::fmt::{self, Debug, Display};
 use core::mem::ManuallyDrop;
+use core::panic::Location;
 #[cfg(not(anyhow_no_ptr_addr_of))]
 use core::ptr;
 use core::ptr::NonNull;
@@ -29,12 +46,18 @@ impl Error {
     #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn new<E>(error: E) -> Self
     where
         E: StdError + Send + Sync + 'static,
     {
-        let backtrace = backtrace_if_absent!(&error);
-        Error::from_std(error, backtrace)
+        match shared_error(error) {
+            Ok(shared) => Error::from_shared(shared),
+            Err(error) => {
+                let backtrace = backtrace_if_absent!(&error);
+                Error::from_std(error, backtrace)
+            }
+        }
     }
 
     /// Create a new error object from a printable error message.
@@ -76,6 +99,7 @@ impl Error {
     /// ```
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn msg<M>(message: M) -> Self
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -83,8 +107,98 @@ impl Error {
         Error::from_adhoc(message, backtrace!())
     }
 
+    /// Create an error from the payload of a caught panic.
+    ///
+    /// This is meant for the `Err` of [`std::panic::catch_unwind`]. The
+    /// error's Display representation includes the panic message if the
+    /// payload is a `&str` or `String`, as it is for panics raised by
+    /// `panic!`, and the resulting error can be downcast to
+    /// [`Panic`][crate::Panic].
+    ///
+    /// If the panic happened on the current thread while anyhow's panic hook
+    /// was installed, the error also has the location of the panic, the name
+    /// of the thread, and the backtrace that was captured at the panic. See
+    /// [`install_panic_hook`][crate::install_panic_hook]. For the payload of
+    /// a panic on another thread, such as the `Err` of `JoinHandle::join`,
+    /// use [`from_thread_panic`][Error::from_thread_panic] instead.
+    ///
+    /// [`catch_unwind`][crate::catch_unwind] and [`join`][crate::join] are
+    /// shorthands for the common uses of this function.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Error, Result};
+    /// use std::panic;
+    ///
+    /// fn run_task(task: fn()) -> Result<()> {
+    ///     panic::catch_unwind(task).map_err(Error::from_panic)
+    /// }
+    /// #
+    /// # fn main() {
+    /// #     panic::set_hook(Box::new(|_| {}));
+    /// #     let error = run_task(|| panic!("oh no!")).unwrap_err();
+    /// #     assert!(error.to_string().ends_with("panicked: oh no!"));
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn from_panic(payload: Box<dyn core::any::Any + Send>) -> Self {
+        // Without a record from the panic hook, there is no telling whether
+        // the payload came from this thread.
+        let thread = std::thread::current();
+        let (panic, backtrace) = crate::panic::recover(payload, thread.id(), None);
+        crate::panic::into_error(panic, backtrace)
+    }
+
+    /// Create an error from the payload of a panic on the given thread.
+    ///
+    /// This is meant for the `Err` of `JoinHandle::join`, with the
+    /// [`Thread`][std::thread::Thread] of the handle. It is otherwise the same
+    /// as [`from_panic`][Error::from_panic], and the name of that thread is
+    /// known even if anyhow's panic hook was not installed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{Error, Result};
+    /// use std::thread;
+    ///
+    /// fn run_task(task: fn()) -> Result<()> {
+    ///     let handle = thread::Builder::new()
+    ///         .name("task".to_owned())
+    ///         .spawn(task)?;
+    ///     let thread = handle.thread().clone();
+    ///     handle
+    ///         .join()
+    ///         .map_err(|payload| Error::from_thread_panic(payload, &thread))
+    /// }
+    /// #
+    /// # fn main() {
+    /// #     std::panic::set_hook(Box::new(|_| {}));
+    /// #     let error = run_task(|| panic!("oh no!")).unwrap_err();
+    /// #     assert_eq!(error.to_string(), "thread 'task' panicked: oh no!");
+    /// # }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn from_thread_panic(
+        payload: Box<dyn core::any::Any + Send>,
+        thread: &std::thread::Thread,
+    ) -> Self {
+        let (panic, backtrace) = crate::panic::recover(payload, thread.id(), thread.name());
+        crate::panic::into_error(panic, backtrace)
+    }
+
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_std<E>(error: E, backtrace: Option<Backtrace>) -> Self
     where
         E: StdError + Send + Sync + 'static,
@@ -96,18 +210,103 @@ impl Error {
             object_mut: object_mut::<E>,
             object_boxed: object_boxed::<E>,
             object_downcast: object_downcast::<E>,
+            object_downcast_ref: object_downcast::<E>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<E>,
             object_drop_rest: object_drop_front::<E>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<E>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: no_backtrace,
         };
 
+        let handler = handler::capture(&error);
+
         // Safety: passing vtable that operates on the right type E.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
     }
 
+    // Unlike the other constructors, this accepts an error that is neither Send
+    // nor Sync. Unsafe because the resulting Error must only ever be handed out
+    // inside of a LocalError, which is neither Send nor Sync either.
+    #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) unsafe fn from_local<E>(error: E, backtrace: Option<Backtrace>) -> Self
+    where
+        E: StdError + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<E>,
+            object_ref: local_object_ref::<E>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_mut: local_object_mut::<E>,
+            object_boxed: local_object_boxed::<E>,
+            object_downcast: object_downcast::<E>,
+            object_downcast_ref: object_downcast::<E>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: object_downcast_mut::<E>,
+            object_drop_rest: object_drop_front::<E>,
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<E>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: no_backtrace,
+        };
+
+        // A report handler is given its error as an &Error, which is Send and
+        // Sync, so it must not be given this one.
+        let handler = None;
+
+        // Safety: passing vtable that operates on the right type E.
+        Error::construct(error, vtable, backtrace, handler)
+    }
+
+    // Like context, for a context that is neither Send nor Sync. Unsafe for
+    // the same reason as from_local.
+    #[cfg(feature = "std")]
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) unsafe fn local_context<C>(self, context: C) -> Self
+    where
+        C: Display + 'static,
+    {
+        let error: ContextError<C, Error> = ContextError {
+            context,
+            error: self,
+        };
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, Error>>,
+            object_ref: local_object_ref::<ContextError<C, Error>>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_mut: local_object_mut::<ContextError<C, Error>>,
+            object_boxed: local_object_boxed::<ContextError<C, Error>>,
+            object_downcast: context_chain_downcast::<C>,
+            object_downcast_ref: context_chain_downcast_ref::<C>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: context_chain_downcast_mut::<C>,
+            object_drop_rest: context_chain_drop_rest::<C>,
+            object_inner: context_chain_inner::<C>,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<C>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: context_backtrace::<C>,
+        };
+
+        // As the cause is anyhow::Error, we already have a backtrace for it.
+        // The report handler of the cause, if it has one, must not be given
+        // this error, so the lookup of the handler stops here.
+        let backtrace = None;
+        let handler: Option<Box<dyn ReportHandler>> = Some(Box::new(crate::DefaultHandler));
+
+        // Safety: passing vtable that operates on the right type.
+        Error::construct(error, vtable, backtrace, handler)
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_adhoc<M>(message: M, backtrace: Option<Backtrace>) -> Self
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -121,19 +320,97 @@ impl Error {
             object_mut: object_mut::<MessageError<M>>,
             object_boxed: object_boxed::<MessageError<M>>,
             object_downcast: object_downcast::<M>,
+            object_downcast_ref: object_downcast::<M>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<M>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: no_backtrace,
         };
 
+        let handler = handler::capture(&error);
+
         // Safety: MessageError is repr(transparent) so it is okay for the
         // vtable to allow casting the MessageError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_condition(condition: ConditionFailed, backtrace: Option<Backtrace>) -> Self {
+        // Without std there is no std::error::Error for ConditionFailed to
+        // implement, so it goes through the same wrapper as a message.
+        #[cfg(feature = "std")]
+        type Object = ConditionFailed;
+        #[cfg(not(feature = "std"))]
+        type Object = crate::wrapper::MessageError<ConditionFailed>;
+
+        #[cfg(feature = "std")]
+        let error: Object = condition;
+        #[cfg(not(feature = "std"))]
+        let error: Object = crate::wrapper::MessageError(condition);
+
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<Object>,
+            object_ref: object_ref::<Object>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<Object>,
+            object_boxed: object_boxed::<Object>,
+            object_downcast: condition_downcast,
+            object_downcast_ref: condition_downcast,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: condition_downcast_mut,
+            object_drop_rest: condition_drop_rest,
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<ConditionFailed>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: no_backtrace,
+        };
+
+        let handler = handler::capture(&error);
+
+        // Safety: MessageError is repr(transparent) so it is okay for the
+        // vtable to allow casting either Object to ConditionFailed.
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
     }
 
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_formatted(message: FormattedMessage, backtrace: Option<Backtrace>) -> Self {
+        use crate::wrapper::MessageError;
+        let error: MessageError<FormattedMessage> = MessageError(message);
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<MessageError<FormattedMessage>>,
+            object_ref: object_ref::<MessageError<FormattedMessage>>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<MessageError<FormattedMessage>>,
+            object_boxed: object_boxed::<MessageError<FormattedMessage>>,
+            object_downcast: formatted_downcast,
+            object_downcast_ref: formatted_downcast,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: formatted_downcast_mut,
+            object_drop_rest: formatted_drop_rest,
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<String>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: no_backtrace,
+        };
+
+        let handler = handler::capture(&error);
+
+        // Safety: MessageError is repr(transparent) so it is okay for the
+        // vtable to allow casting the MessageError<FormattedMessage> to
+        // FormattedMessage.
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_display<M>(message: M, backtrace: Option<Backtrace>) -> Self
     where
         M: Display + Send + Sync + 'static,
@@ -147,20 +424,27 @@ impl Error {
             object_mut: object_mut::<DisplayError<M>>,
             object_boxed: object_boxed::<DisplayError<M>>,
             object_downcast: object_downcast::<M>,
+            object_downcast_ref: object_downcast::<M>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<M>,
             object_drop_rest: object_drop_front::<M>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<M>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: no_backtrace,
         };
 
+        let handler = handler::capture(&error);
+
         // Safety: DisplayError is repr(transparent) so it is okay for the
         // vtable to allow casting the DisplayError<M> to M.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
     }
 
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_context<C, E>(context: C, error: E, backtrace: Option<Backtrace>) -> Self
     where
         C: Display + Send + Sync + 'static,
@@ -175,19 +459,26 @@ impl Error {
             object_mut: object_mut::<ContextError<C, E>>,
             object_boxed: object_boxed::<ContextError<C, E>>,
             object_downcast: context_downcast::<C, E>,
+            object_downcast_ref: context_downcast::<C, E>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_downcast_mut::<C, E>,
             object_drop_rest: context_drop_rest::<C, E>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: context_type_name::<C, E>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: no_backtrace,
         };
 
+        let handler = handler::capture(&error);
+
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
     }
 
     #[cfg(feature = "std")]
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub(crate) fn from_boxed(
         error: Box<dyn StdError + Send + Sync>,
         backtrace: Option<Backtrace>,
@@ -201,16 +492,146 @@ impl Error {
             object_mut: object_mut::<BoxedError>,
             object_boxed: object_boxed::<BoxedError>,
             object_downcast: object_downcast::<Box<dyn StdError + Send + Sync>>,
+            object_downcast_ref: object_downcast::<Box<dyn StdError + Send + Sync>>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: object_downcast_mut::<Box<dyn StdError + Send + Sync>>,
             object_drop_rest: object_drop_front::<Box<dyn StdError + Send + Sync>>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: no_type_name,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: no_backtrace,
         };
 
+        let handler = handler::capture(&error);
+
         // Safety: BoxedError is repr(transparent) so it is okay for the vtable
         // to allow casting to Box<dyn StdError + Send + Sync>.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_errors(errors: Errors, backtrace: Option<Backtrace>) -> Self {
+        use crate::wrapper::AggregateError;
+        let error = AggregateError(errors);
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<AggregateError>,
+            object_ref: object_ref::<AggregateError>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<AggregateError>,
+            object_boxed: object_boxed::<AggregateError>,
+            object_downcast: object_downcast::<Errors>,
+            object_downcast_ref: aggregate_downcast_ref,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: object_downcast_mut::<Errors>,
+            object_drop_rest: object_drop_front::<Errors>,
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<Errors>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: no_backtrace,
+        };
+
+        let handler = handler::capture(&error);
+
+        // Safety: AggregateError is repr(transparent) so it is okay for the
+        // vtable to allow casting the AggregateError to Errors.
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    #[cfg(feature = "serde")]
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_remote(error: RemoteError, backtrace: Option<Backtrace>) -> Self {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<RemoteError>,
+            object_ref: object_ref::<RemoteError>,
+            #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+            object_mut: object_mut::<RemoteError>,
+            object_boxed: object_boxed::<RemoteError>,
+            object_downcast: object_downcast::<RemoteError>,
+            object_downcast_ref: object_downcast::<RemoteError>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: object_downcast_mut::<RemoteError>,
+            object_drop_rest: object_drop_front::<RemoteError>,
+            object_inner: no_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: remote_type_name,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: no_backtrace,
+        };
+
+        let handler = handler::capture(&error);
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    #[cfg(feature = "std")]
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub(crate) fn from_shared(error: SharedError) -> Self {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<SharedError>,
+            object_ref: object_ref::<SharedError>,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_mut: object_mut::<SharedError>,
+            object_boxed: object_boxed::<SharedError>,
+            object_downcast: object_downcast::<SharedError>,
+            object_downcast_ref: shared_downcast_ref,
+            #[cfg(anyhow_no_ptr_addr_of)]
+            object_downcast_mut: object_downcast_mut::<SharedError>,
+            object_drop_rest: object_drop_front::<SharedError>,
+            object_inner: shared_inner,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: shared_type_name,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+            object_backtrace: shared_backtrace,
+        };
+
+        // The shared error already holds a backtrace if one was captured, and
+        // its report handler if one was installed.
+        let backtrace = None;
+        let handler = None;
+
+        // Safety: passing vtable that operates on the right type.
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    /// Convert this error into a [`SharedError`], which is cheaply cloneable.
+    ///
+    /// This is the same as `SharedError::from(error)`.
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    #[cold]
+    #[must_use]
+    pub fn into_shared(self) -> SharedError {
+        SharedError::from(self)
+    }
+
+    /// Returns true if this error, or an error that it wraps, was
+    /// reconstructed by deserializing a [`Report`][crate::Report].
+    ///
+    /// The layers of a deserialized error are opaque: they reproduce the
+    /// Display and Debug representations and the type names of the original
+    /// chain, but the original error types cannot be downcast to.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+    pub fn is_remote(&self) -> bool {
+        self.downcast_ref::<RemoteError>().is_some()
+    }
+
+    /// The backtrace captured by the process that originally produced this
+    /// error, if it was reconstructed by deserializing a
+    /// [`Report`][crate::Report] that contained one.
+    ///
+    /// This is kept separate from the error's own backtrace, which is captured
+    /// locally at the point that the report is deserialized.
+    #[cfg(feature = "serde")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+    pub fn remote_backtrace(&self) -> Option<&RemoteBacktrace> {
+        self.downcast_ref::<RemoteError>()?.backtrace.as_ref()
     }
 
     // Takes backtrace as argument rather than capturing it here so that the
@@ -219,17 +640,33 @@ impl Error {
     // Unsafe because the given vtable must have sensible behavior on the error
     // value of type E.
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     unsafe fn construct<E>(
         error: E,
         vtable: &'static ErrorVTable,
         backtrace: Option<Backtrace>,
+        handler: Option<Box<dyn ReportHandler>>,
     ) -> Self
     where
-        E: StdError + Send + Sync + 'static,
+        E: StdError + 'static,
     {
+        #[cfg(not(anyhow_no_track_caller))]
+        #[allow(clippy::incompatible_msrv)]
+        let location = Some(Location::caller());
+        #[cfg(anyhow_no_track_caller)]
+        let location = None;
+
+        #[cfg(feature = "std")]
+        let attachments = crate::scope::capture();
+        #[cfg(not(feature = "std"))]
+        let attachments = Vec::new();
+
         let inner: Box<ErrorImpl<E>> = Box::new(ErrorImpl {
             vtable,
             backtrace,
+            location,
+            attachments,
+            handler,
             _object: error,
         });
         // Erase the concrete type of E from the compile-time type system. This
@@ -298,6 +735,7 @@ impl Error {
     /// ```
     #[cold]
     #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn context<C>(self, context: C) -> Self
     where
         C: Display + Send + Sync + 'static,
@@ -314,18 +752,139 @@ impl Error {
             object_mut: object_mut::<ContextError<C, Error>>,
             object_boxed: object_boxed::<ContextError<C, Error>>,
             object_downcast: context_chain_downcast::<C>,
+            object_downcast_ref: context_chain_downcast_ref::<C>,
             #[cfg(anyhow_no_ptr_addr_of)]
             object_downcast_mut: context_chain_downcast_mut::<C>,
             object_drop_rest: context_chain_drop_rest::<C>,
-            #[cfg(all(not(backtrace), feature = "backtrace"))]
+            object_inner: context_chain_inner::<C>,
+            #[cfg(any(feature = "std", feature = "serde"))]
+            object_type_name: object_type_name::<C>,
+            #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
             object_backtrace: context_backtrace::<C>,
         };
 
-        // As the cause is anyhow::Error, we already have a backtrace for it.
+        // As the cause is anyhow::Error, we already have a backtrace and a
+        // report handler for it.
         let backtrace = None;
+        let handler = None;
 
         // Safety: passing vtable that operates on the right type.
-        unsafe { Error::construct(error, vtable, backtrace) }
+        unsafe { Error::construct(error, vtable, backtrace, handler) }
+    }
+
+    /// Attach a machine-readable value to the error without affecting its
+    /// Display representation.
+    ///
+    /// Attachments are not a layer of the error's cause chain. They are carried
+    /// alongside the error, survive any amount of context being added on top,
+    /// and can be retrieved by type using [`attachment`][Error::attachment]
+    /// or [`attachments`][Error::attachments].
+    ///
+    /// Values attached this way are not rendered in any of the error's
+    /// Display or Debug representations. Use
+    /// [`attach_printable`][Error::attach_printable] for values that should
+    /// appear in the `{:?}` report.
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Result};
+    ///
+    /// pub struct StatusCode(pub u16);
+    ///
+    /// fn fetch() -> Result<()> {
+    ///     Err(anyhow!("upstream unavailable").attach(StatusCode(503)))
+    /// }
+    ///
+    /// fn main() {
+    ///     let error = fetch().unwrap_err().context("failed to load profile");
+    ///     let status = error.attachment::<StatusCode>().unwrap();
+    ///     assert_eq!(status.0, 503);
+    /// }
+    /// ```
+    #[cold]
+    #[must_use]
+    pub fn attach<T>(mut self, value: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        let attachment = Attachment::new(value);
+        unsafe { ErrorImpl::attachments_mut(self.inner.by_mut()).push(attachment) }
+        self
+    }
+
+    /// Attach a value to the error which is also rendered in the error's
+    /// `{:?}` report, beneath the causes.
+    ///
+    /// ```console
+    /// Error: failed to load profile
+    ///
+    /// Caused by:
+    ///     upstream unavailable
+    ///
+    /// Attachments:
+    ///     request id 7f3a
+    /// ```
+    ///
+    /// The value can be retrieved by type the same way as one attached by
+    /// [`attach`][Error::attach].
+    #[cold]
+    #[must_use]
+    pub fn attach_printable<T>(mut self, value: T) -> Self
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        let attachment = Attachment::printable(value);
+        unsafe { ErrorImpl::attachments_mut(self.inner.by_mut()).push(attachment) }
+        self
+    }
+
+    /// The most recently attached value of type `T`, searching every layer
+    /// of context from the outermost inward.
+    pub fn attachment<T>(&self) -> Option<&T>
+    where
+        T: 'static,
+    {
+        self.attachments::<T>().next()
+    }
+
+    /// An iterator of every attached value of type `T`.
+    ///
+    /// Attachments on the outermost layer of context are visited first, and
+    /// within one layer the most recently attached value comes first.
+    pub fn attachments<T>(&self) -> Attachments<'_, T>
+    where
+        T: 'static,
+    {
+        Attachments::new(self.inner.by_ref())
+    }
+
+    /// The source location at which this error was created, or at which the
+    /// outermost context was attached to it.
+    ///
+    /// The location is captured by `Error::new`, `Error::msg`, the `anyhow!`,
+    /// `bail!` and `ensure!` macros, conversion by `?`, and the
+    /// [`Context`][crate::Context] methods. Unlike a backtrace this is always
+    /// captured, regardless of `RUST_BACKTRACE`.
+    ///
+    /// The location of each layer of context is available from
+    /// [`Chain::with_locations`][crate::Chain::with_locations]. Setting the
+    /// environment variable `ANYHOW_LOCATION=1` includes the locations in the
+    /// `{:?}` representation of the error.
+    ///
+    /// Returns `None` on compilers older than Rust 1.46, which do not support
+    /// `#[track_caller]`.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        unsafe { ErrorImpl::location(self.inner.by_ref()) }
+    }
+
+    /// The report handler that renders this error's Debug representation, if
+    /// one was installed by [`set_report_handler`][crate::set_report_handler]
+    /// before the error was created.
+    ///
+    /// Use [`downcast_ref`][ReportHandler#method.downcast_ref] on the result
+    /// to read any state that the handler captured when the error was
+    /// created.
+    pub fn report_handler(&self) -> Option<&dyn ReportHandler> {
+        unsafe { ErrorImpl::handler(self.inner.by_ref()) }
     }
 
     /// Get the backtrace for this Error.
@@ -345,25 +904,65 @@ impl Error {
     ///
     /// # Stability
     ///
-    /// Standard library backtraces are only available on the nightly channel.
-    /// Tracking issue: [rust-lang/rust#53487][tracking].
+    /// On Rust 1.65 and newer, this returns the standard library's
+    /// `std::backtrace::Backtrace`. Picking up a backtrace that the underlying
+    /// error provides itself, rather than capturing a new one, is only
+    /// possible on the nightly channel. Tracking issue:
+    /// [rust-lang/rust#99301][tracking].
     ///
-    /// On stable compilers, this function is only available if the crate's
-    /// "backtrace" feature is enabled, and will use the `backtrace` crate as
-    /// the underlying backtrace implementation.
+    /// On older compilers, this function is only available if the crate's
+    /// "backtrace" feature is enabled. Enabling that feature on any compiler
+    /// except nightly uses the `backtrace` crate as the underlying backtrace
+    /// implementation instead of the standard library, which additionally
+    /// reports [instruction pointers][crate::BacktraceFrame::ip].
     ///
     /// ```toml
     /// [dependencies]
     /// anyhow = { version = "1.0", features = ["backtrace"] }
     /// ```
     ///
-    /// [tracking]: https://github.com/rust-lang/rust/issues/53487
-    #[cfg(any(backtrace, feature = "backtrace"))]
-    #[cfg_attr(doc_cfg, doc(cfg(any(nightly, feature = "backtrace"))))]
+    /// [tracking]: https://github.com/rust-lang/rust/issues/99301
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     pub fn backtrace(&self) -> &impl_backtrace!() {
         unsafe { ErrorImpl::backtrace(self.inner.by_ref()) }
     }
 
+    /// The symbols of the captured backtrace, innermost call first.
+    ///
+    /// This is the structured counterpart of [`backtrace`][Error::backtrace],
+    /// for tooling that wants to inspect the frames rather than print them.
+    /// It is empty if no backtrace was captured.
+    ///
+    /// ```
+    /// # use anyhow::anyhow;
+    /// #
+    /// let error = anyhow!("oh no!");
+    /// for frame in error.backtrace_frames() {
+    ///     if let (Some(name), Some(file)) = (frame.name(), frame.file()) {
+    ///         println!("{} at {}", name, file.display());
+    ///     }
+    /// }
+    /// ```
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn backtrace_frames(&self) -> BacktraceFrames {
+        BacktraceFrames::new(unsafe { ErrorImpl::backtrace(self.inner.by_ref()) })
+    }
+
+    /// The captured backtrace as raw instruction pointers, to be symbolized
+    /// later or elsewhere.
+    ///
+    /// Returns `None` if no backtrace was captured. This is also the case on
+    /// the nightly channel, where the standard library's backtrace is used,
+    /// which does not give access to instruction pointers.
+    #[cfg(feature = "symbolize")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "symbolize")))]
+    pub fn raw_backtrace(&self) -> Option<RawBacktrace> {
+        let backtrace = unsafe { ErrorImpl::backtrace(self.inner.by_ref()) };
+        RawBacktrace::capture(crate::backtrace::ips(backtrace))
+    }
+
     /// An iterator of the chain of source errors contained by this Error.
     ///
     /// This iterator will visit every error in the cause chain of this error
@@ -388,7 +987,7 @@ impl Error {
     #[cfg(feature = "std")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
     #[cold]
-    pub fn chain(&self) -> Chain {
+    pub fn chain(&self) -> Chain<'_> {
         unsafe { ErrorImpl::chain(self.inner.by_ref()) }
     }
 
@@ -403,6 +1002,91 @@ impl Error {
         self.chain().last().unwrap()
     }
 
+    /// A hash that identifies where and how this error was produced, for
+    /// grouping reports of the same failure.
+    ///
+    /// The hash is built from the type of every error in the
+    /// [`chain()`][Error::chain], and from the parts of their messages that
+    /// do not vary from one occurrence to the next: string literal messages
+    /// and contexts, the format string of messages created by [`anyhow!`] and
+    /// [`bail!`], and the condition of a failed [`ensure!`]. The values
+    /// interpolated into a message do not take part, so the same failure
+    /// hashes the same regardless of which file or which request it was
+    /// about.
+    ///
+    /// The hash is computed with a fixed algorithm and is the same across
+    /// runs and across machines for a given build of the program. It may
+    /// change when the program is rebuilt with a different compiler, since
+    /// type names are involved.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use anyhow::{anyhow, Context, Result};
+    ///
+    /// fn load(path: &str) -> Result<()> {
+    ///     Err(anyhow!("no such file: {}", path)).context("failed to load config")
+    /// }
+    ///
+    /// let a = load("a.toml").unwrap_err();
+    /// let b = load("b.toml").unwrap_err();
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// println!("{:016x}", a.fingerprint());
+    /// ```
+    ///
+    /// [`anyhow!`]: crate::anyhow
+    /// [`bail!`]: crate::bail
+    /// [`ensure!`]: crate::ensure
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn fingerprint(&self) -> u64 {
+        unsafe { crate::fingerprint::fingerprint(self.inner.by_ref(), 0) }
+    }
+
+    /// Like [`fingerprint`][Error::fingerprint], but also hashing the names
+    /// of the innermost `frames` functions of the backtrace.
+    ///
+    /// Only frames of the program itself are counted: the frames of anyhow,
+    /// of the backtrace capture and of the standard library are skipped. This
+    /// tells apart errors that are produced the same way from different
+    /// callers. If no backtrace was captured, the result is the same as that
+    /// of `fingerprint`.
+    #[cfg(all(feature = "std", any(std_backtrace, feature = "backtrace")))]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn fingerprint_with_frames(&self, frames: usize) -> u64 {
+        unsafe { crate::fingerprint::fingerprint(self.inner.by_ref(), frames) }
+    }
+
+    /// Display and Debug adapter that renders this error with sensitive data
+    /// hidden, for logs that are shared more widely than the error itself.
+    ///
+    /// The output is the same as that of the error's own Display and Debug
+    /// representations, except that values wrapped in
+    /// [`Sensitive`][crate::Sensitive] are shown as `<redacted>`, and so is
+    /// any text in the messages that matches one of the redactors registered
+    /// with [`add_redactor`][crate::add_redactor].
+    ///
+    /// ```
+    /// use anyhow::{Context, Result, Sensitive};
+    /// use std::fs;
+    ///
+    /// fn read_credentials(path: &str) -> Result<String> {
+    ///     fs::read_to_string(path).context(Sensitive(format!("failed to read {}", path)))
+    /// }
+    ///
+    /// # fn upload_to_shared_log(_: String) {}
+    /// #
+    /// if let Err(error) = read_credentials("/home/alice/.credentials") {
+    ///     eprintln!("{:?}", error);
+    ///     upload_to_shared_log(format!("{:?}", error.redacted()));
+    /// }
+    /// ```
+    #[cfg(feature = "std")]
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+    pub fn redacted(&self) -> Redacted<'_> {
+        Redacted::new(self)
+    }
+
     /// Returns true if `E` is the type held by this error object.
     ///
     /// For errors with context, this method returns true if `E` matches the
@@ -419,9 +1103,18 @@ impl Error {
     }
 
     /// Attempt to downcast the error object to a concrete type.
-    pub fn downcast<E>(mut self) -> Result<E, Self>
+    pub fn downcast<E>(self) -> Result<E, Self>
     where
         E: Display + Debug + Send + Sync + 'static,
+    {
+        self.downcast_any()
+    }
+
+    // Downcasts without requiring E to be Send and Sync, which only an Error
+    // held by a LocalError can contain.
+    pub(crate) fn downcast_any<E>(mut self) -> Result<E, Self>
+    where
+        E: 'static,
     {
         let target = TypeId::of::<E>();
         let inner = self.inner.by_mut();
@@ -492,12 +1185,19 @@ impl Error {
     pub fn downcast_ref<E>(&self) -> Option<&E>
     where
         E: Display + Debug + Send + Sync + 'static,
+    {
+        self.downcast_ref_any()
+    }
+
+    pub(crate) fn downcast_ref_any<E>(&self) -> Option<&E>
+    where
+        E: 'static,
     {
         let target = TypeId::of::<E>();
         unsafe {
             // Use vtable to find NonNull<()> which points to a value of type E
             // somewhere inside the data structure.
-            let addr = (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?;
+            let addr = (vtable(self.inner.ptr).object_downcast_ref)(self.inner.by_ref(), target)?;
             Some(addr.cast::<E>().deref())
         }
     }
@@ -506,6 +1206,13 @@ impl Error {
     pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
     where
         E: Display + Debug + Send + Sync + 'static,
+    {
+        self.downcast_mut_any()
+    }
+
+    pub(crate) fn downcast_mut_any<E>(&mut self) -> Option<&mut E>
+    where
+        E: 'static,
     {
         let target = TypeId::of::<E>();
         unsafe {
@@ -543,9 +1250,32 @@ where
     E: StdError + Send + Sync + 'static,
 {
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     fn from(error: E) -> Self {
-        let backtrace = backtrace_if_absent!(&error);
-        Error::from_std(error, backtrace)
+        match shared_error(error) {
+            Ok(shared) => Error::from_shared(shared),
+            Err(error) => {
+                let backtrace = backtrace_if_absent!(&error);
+                Error::from_std(error, backtrace)
+            }
+        }
+    }
+}
+
+// Converting a SharedError back into Error should not introduce an opaque
+// layer that hides the original error from downcast_ref. Without
+// specialization, the SharedError case is picked out by TypeId.
+#[cfg(feature = "std")]
+fn shared_error<E>(error: E) -> Result<SharedError, E>
+where
+    E: 'static,
+{
+    if TypeId::of::<E>() == TypeId::of::<SharedError>() {
+        let error = ManuallyDrop::new(error);
+        // Safety: E and SharedError are the same type.
+        Ok(unsafe { core::ptr::read(&*error as *const E as *const SharedError) })
+    } else {
+        Err(error)
     }
 }
 
@@ -575,7 +1305,10 @@ impl Display for Error {
 
 impl Debug for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        unsafe { ErrorImpl::debug(self.inner.by_ref(), formatter) }
+        match self.report_handler() {
+            Some(handler) => handler.debug(self, formatter),
+            None => unsafe { ErrorImpl::debug(self.inner.by_ref(), formatter) },
+        }
     }
 }
 
@@ -595,10 +1328,17 @@ struct ErrorVTable {
     object_mut: unsafe fn(Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static),
     object_boxed: unsafe fn(Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>,
     object_downcast: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
+    // Same as object_downcast, but the result is only ever used for shared
+    // access so it is allowed to look inside of a SharedError.
+    object_downcast_ref: unsafe fn(Ref<ErrorImpl>, TypeId) -> Option<Ref<()>>,
     #[cfg(anyhow_no_ptr_addr_of)]
     object_downcast_mut: unsafe fn(Mut<ErrorImpl>, TypeId) -> Option<Mut<()>>,
     object_drop_rest: unsafe fn(Own<ErrorImpl>, TypeId),
-    #[cfg(all(not(backtrace), feature = "backtrace"))]
+    object_inner: unsafe fn(Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>,
+    #[cfg(any(feature = "std", feature = "serde"))]
+    object_type_name:
+        for<'a> unsafe fn(Ref<'a, ErrorImpl>, &(dyn StdError + 'static)) -> Option<&'a str>,
+    #[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
     object_backtrace: unsafe fn(Ref<ErrorImpl>) -> Option<&Backtrace>,
 }
 
@@ -658,6 +1398,46 @@ where
     e.cast::<ErrorImpl<E>>().boxed()
 }
 
+// Safety: requires layout of *e to match ErrorImpl<E>, and for the Error to
+// be confined to one thread by a LocalError. The Send and Sync in the return
+// type are not true of E, but the vtable of dyn StdError is the same either way.
+#[cfg(feature = "std")]
+unsafe fn local_object_ref<E>(e: Ref<ErrorImpl>) -> Ref<dyn StdError + Send + Sync + 'static>
+where
+    E: StdError + 'static,
+{
+    let unerased = e.cast::<ErrorImpl<E>>();
+
+    #[cfg(not(anyhow_no_ptr_addr_of))]
+    let object: *const (dyn StdError + 'static) = ptr::addr_of!((*unerased.as_ptr())._object);
+
+    #[cfg(anyhow_no_ptr_addr_of)]
+    let object: *const (dyn StdError + 'static) = &unerased.deref()._object;
+
+    let object: *const (dyn StdError + Send + Sync + 'static) = core::mem::transmute(object);
+    Ref::from_raw(NonNull::new_unchecked(object as *mut _))
+}
+
+// Safety: as for local_object_ref, and for `e` to be derived from a `&mut`.
+#[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
+unsafe fn local_object_mut<E>(e: Mut<ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static)
+where
+    E: StdError + 'static,
+{
+    let object: &mut (dyn StdError + 'static) = &mut e.cast::<ErrorImpl<E>>().deref_mut()._object;
+    core::mem::transmute(object)
+}
+
+// Safety: as for local_object_ref.
+#[cfg(feature = "std")]
+unsafe fn local_object_boxed<E>(e: Own<ErrorImpl>) -> Box<dyn StdError + Send + Sync + 'static>
+where
+    E: StdError + 'static,
+{
+    let boxed: Box<dyn StdError + 'static> = e.cast::<ErrorImpl<E>>().boxed();
+    core::mem::transmute(boxed)
+}
+
 // Safety: requires layout of *e to match ErrorImpl<E>.
 unsafe fn object_downcast<E>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
 where
@@ -700,8 +1480,140 @@ where
     }
 }
 
-#[cfg(all(not(backtrace), feature = "backtrace"))]
-fn no_backtrace(e: Ref<ErrorImpl>) -> Option<&Backtrace> {
+// Safety: requires layout of *e to match ErrorImpl<MessageError<FormattedMessage>>.
+unsafe fn formatted_downcast(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    if TypeId::of::<String>() == target {
+        let unerased = e.cast::<ErrorImpl<FormattedMessage>>();
+
+        #[cfg(not(anyhow_no_ptr_addr_of))]
+        return Some(
+            Ref::from_raw(NonNull::new_unchecked(
+                ptr::addr_of!((*unerased.as_ptr())._object.message) as *mut String,
+            ))
+            .cast::<()>(),
+        );
+
+        #[cfg(anyhow_no_ptr_addr_of)]
+        return Some(Ref::new(&unerased.deref()._object.message).cast::<()>());
+    } else {
+        object_downcast::<FormattedMessage>(e, target)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<MessageError<FormattedMessage>>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn formatted_downcast_mut(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>> {
+    if TypeId::of::<String>() == target {
+        let unerased = e.cast::<ErrorImpl<FormattedMessage>>().deref_mut();
+        Some(Mut::new(&mut unerased._object.message).cast::<()>())
+    } else {
+        object_downcast_mut::<FormattedMessage>(e, target)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<MessageError<FormattedMessage>>.
+unsafe fn formatted_drop_rest(e: Own<ErrorImpl>, target: TypeId) {
+    // Called after downcasting by value to either the FormattedMessage or its
+    // String and doing a ptr::read to take ownership of that value.
+    let mut unerased = e
+        .cast::<ErrorImpl<ManuallyDrop<FormattedMessage>>>()
+        .boxed();
+    if TypeId::of::<String>() == target {
+        let message = &mut *unerased._object;
+        core::ptr::drop_in_place(&mut message.args);
+        core::ptr::drop_in_place(&mut message.named_args);
+        #[cfg(feature = "std")]
+        core::ptr::drop_in_place(&mut message.sensitive);
+    }
+    drop(unerased);
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ConditionFailed>.
+unsafe fn condition_downcast(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    if TypeId::of::<String>() == target {
+        let unerased = e.cast::<ErrorImpl<ConditionFailed>>();
+
+        #[cfg(not(anyhow_no_ptr_addr_of))]
+        return Some(
+            Ref::from_raw(NonNull::new_unchecked(
+                ptr::addr_of!((*unerased.as_ptr())._object.message) as *mut String,
+            ))
+            .cast::<()>(),
+        );
+
+        #[cfg(anyhow_no_ptr_addr_of)]
+        return Some(Ref::new(&unerased.deref()._object.message).cast::<()>());
+    } else {
+        object_downcast::<ConditionFailed>(e, target)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ConditionFailed>.
+#[cfg(anyhow_no_ptr_addr_of)]
+unsafe fn condition_downcast_mut(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>> {
+    if TypeId::of::<String>() == target {
+        let unerased = e.cast::<ErrorImpl<ConditionFailed>>().deref_mut();
+        Some(Mut::new(&mut unerased._object.message).cast::<()>())
+    } else {
+        object_downcast_mut::<ConditionFailed>(e, target)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ConditionFailed>.
+unsafe fn condition_drop_rest(e: Own<ErrorImpl>, target: TypeId) {
+    // Called after downcasting by value to either the ConditionFailed or its
+    // String and doing a ptr::read to take ownership of that value.
+    let mut unerased = e
+        .cast::<ErrorImpl<ManuallyDrop<ConditionFailed>>>()
+        .boxed();
+    if TypeId::of::<String>() == target {
+        let condition = &mut *unerased._object;
+        core::ptr::drop_in_place(&mut condition.lhs);
+        core::ptr::drop_in_place(&mut condition.rhs);
+        core::ptr::drop_in_place(&mut condition.pretty);
+        core::ptr::drop_in_place(&mut condition.clauses);
+    }
+    drop(unerased);
+}
+
+fn no_inner(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>> {
+    let _ = e;
+    None
+}
+
+// Whether `error` is the same object as `object`, ignoring which vtable either
+// reference carries.
+#[cfg(any(feature = "std", feature = "serde"))]
+fn same_object(object: &(dyn StdError + 'static), error: &(dyn StdError + 'static)) -> bool {
+    object as *const dyn StdError as *const () == error as *const dyn StdError as *const ()
+}
+
+// Safety: requires layout of *e to match ErrorImpl<E> for some E whose
+// downcast target is T.
+#[cfg(any(feature = "std", feature = "serde"))]
+unsafe fn object_type_name<'a, T>(
+    e: Ref<'a, ErrorImpl>,
+    error: &(dyn StdError + 'static),
+) -> Option<&'a str> {
+    if same_object(ErrorImpl::error(e), error) {
+        Some(any::type_name::<T>())
+    } else {
+        None
+    }
+}
+
+#[cfg(feature = "std")]
+fn no_type_name<'a>(
+    e: Ref<'a, ErrorImpl>,
+    error: &(dyn StdError + 'static),
+) -> Option<&'a str> {
+    let _ = e;
+    let _ = error;
+    None
+}
+
+#[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+fn no_backtrace(e: Ref<'_, ErrorImpl>) -> Option<&Backtrace> {
     let _ = e;
     None
 }
@@ -724,6 +1636,26 @@ where
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
+#[cfg(feature = "std")]
+unsafe fn context_type_name<'a, C, E>(
+    e: Ref<'a, ErrorImpl>,
+    error: &(dyn StdError + 'static),
+) -> Option<&'a str>
+where
+    C: 'static,
+    E: StdError + 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
+    if same_object(ErrorImpl::error(e), error) {
+        Some(any::type_name::<C>())
+    } else if same_object(&unerased._object.error, error) {
+        Some(any::type_name::<E>())
+    } else {
+        None
+    }
+}
+
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, E>>.
 #[cfg(all(feature = "std", anyhow_no_ptr_addr_of))]
 unsafe fn context_downcast_mut<C, E>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
@@ -779,6 +1711,21 @@ where
     }
 }
 
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+unsafe fn context_chain_downcast_ref<C>(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    if TypeId::of::<C>() == target {
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else {
+        // Recurse down the context chain per the inner error's vtable.
+        let source = &unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast_ref)(source.inner.by_ref(), target)
+    }
+}
+
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
 #[cfg(anyhow_no_ptr_addr_of)]
 unsafe fn context_chain_downcast_mut<C>(e: Mut<ErrorImpl>, target: TypeId) -> Option<Mut<()>>
@@ -822,9 +1769,19 @@ where
 }
 
 // Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
-#[cfg(all(not(backtrace), feature = "backtrace"))]
 #[allow(clippy::unnecessary_wraps)]
-unsafe fn context_backtrace<C>(e: Ref<ErrorImpl>) -> Option<&Backtrace>
+unsafe fn context_chain_inner<C>(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    Some(unerased._object.error.inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<ContextError<C, Error>>.
+#[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn context_backtrace<C>(e: Ref<'_, ErrorImpl>) -> Option<&Backtrace>
 where
     C: 'static,
 {
@@ -833,6 +1790,76 @@ where
     Some(backtrace)
 }
 
+// Safety: requires layout of *e to match ErrorImpl<Errors>.
+unsafe fn aggregate_downcast_ref(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let unerased = e.cast::<ErrorImpl<Errors>>().deref();
+    if TypeId::of::<Errors>() == target {
+        Some(Ref::new(&unerased._object).cast::<()>())
+    } else {
+        // Search each of the independent errors in order.
+        unerased._object.iter().find_map(|error| {
+            (vtable(error.inner.ptr).object_downcast_ref)(error.inner.by_ref(), target)
+        })
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<SharedError>.
+#[cfg(feature = "std")]
+unsafe fn shared_downcast_ref(e: Ref<ErrorImpl>, target: TypeId) -> Option<Ref<()>> {
+    let unerased = e.cast::<ErrorImpl<SharedError>>().deref();
+    if TypeId::of::<SharedError>() == target {
+        Some(Ref::new(&unerased._object).cast::<()>())
+    } else {
+        // Look through to the error held by the Arc. This is only done for
+        // shared access; the value cannot be moved or mutated out of an Arc.
+        let source = unerased._object.as_error();
+        (vtable(source.inner.ptr).object_downcast_ref)(source.inner.by_ref(), target)
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<SharedError>.
+#[cfg(feature = "std")]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn shared_inner(e: Ref<ErrorImpl>) -> Option<Ref<ErrorImpl>> {
+    let unerased = e.cast::<ErrorImpl<SharedError>>().deref();
+    Some(unerased._object.as_error().inner.by_ref())
+}
+
+// Safety: requires layout of *e to match ErrorImpl<SharedError>.
+#[cfg(feature = "std")]
+unsafe fn shared_type_name<'a>(
+    e: Ref<'a, ErrorImpl>,
+    error: &(dyn StdError + 'static),
+) -> Option<&'a str> {
+    if same_object(ErrorImpl::error(e), error) {
+        // The SharedError stands in for the head of the error it holds.
+        let unerased = e.cast::<ErrorImpl<SharedError>>().deref();
+        let inner = unerased._object.as_error().inner.by_ref();
+        ErrorImpl::type_name_of(inner, ErrorImpl::error(inner))
+    } else {
+        None
+    }
+}
+
+// Safety: requires layout of *e to match ErrorImpl<RemoteError>.
+#[cfg(feature = "serde")]
+unsafe fn remote_type_name<'a>(
+    e: Ref<'a, ErrorImpl>,
+    error: &(dyn StdError + 'static),
+) -> Option<&'a str> {
+    let unerased = e.cast::<ErrorImpl<RemoteError>>().deref();
+    unerased._object.type_name_of(error)
+}
+
+// Safety: requires layout of *e to match ErrorImpl<SharedError>.
+#[cfg(all(not(backtrace), any(std_backtrace, feature = "backtrace")))]
+#[allow(clippy::unnecessary_wraps)]
+unsafe fn shared_backtrace(e: Ref<'_, ErrorImpl>) -> Option<&Backtrace> {
+    let unerased = e.cast::<ErrorImpl<SharedError>>().deref();
+    let backtrace = ErrorImpl::backtrace(unerased._object.as_error().inner.by_ref());
+    Some(backtrace)
+}
+
 // NOTE: If working with `ErrorImpl<()>`, references should be avoided in favor
 // of raw pointers and `NonNull`.
 // repr C to ensure that E remains in the final position.
@@ -840,6 +1867,9 @@ where
 pub(crate) struct ErrorImpl<E = ()> {
     vtable: &'static ErrorVTable,
     backtrace: Option<Backtrace>,
+    location: Option<&'static Location<'static>>,
+    attachments: Vec<Attachment>,
+    handler: Option<Box<dyn ReportHandler>>,
     // NOTE: Don't use directly. Use only through vtable. Erased type may have
     // different alignment.
     _object: E,
@@ -861,7 +1891,7 @@ pub(crate) struct ContextError<C, E> {
 }
 
 impl<E> ErrorImpl<E> {
-    fn erase(&self) -> Ref<ErrorImpl> {
+    fn erase(&self) -> Ref<'_, ErrorImpl> {
         // Erase the concrete type of E but preserve the vtable in self.vtable
         // for manipulating the resulting thin pointer. This is analogous to an
         // unsize coercion.
@@ -870,14 +1900,14 @@ impl<E> ErrorImpl<E> {
 }
 
 impl ErrorImpl {
-    pub(crate) unsafe fn error(this: Ref<Self>) -> &(dyn StdError + Send + Sync + 'static) {
+    pub(crate) unsafe fn error(this: Ref<'_, Self>) -> &(dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
         // original type E.
         (vtable(this.ptr).object_ref)(this).deref()
     }
 
     #[cfg(feature = "std")]
-    pub(crate) unsafe fn error_mut(this: Mut<Self>) -> &mut (dyn StdError + Send + Sync + 'static) {
+    pub(crate) unsafe fn error_mut(this: Mut<'_, Self>) -> &mut (dyn StdError + Send + Sync + 'static) {
         // Use vtable to attach E's native StdError vtable for the right
         // original type E.
 
@@ -890,8 +1920,8 @@ impl ErrorImpl {
         return (vtable(this.ptr).object_mut)(this);
     }
 
-    #[cfg(any(backtrace, feature = "backtrace"))]
-    pub(crate) unsafe fn backtrace(this: Ref<Self>) -> &Backtrace {
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    pub(crate) unsafe fn backtrace(this: Ref<'_, Self>) -> &Backtrace {
         // This unwrap can only panic if the underlying error's backtrace method
         // is nondeterministic, which would only happen in maliciously
         // constructed code.
@@ -907,6 +1937,109 @@ impl ErrorImpl {
             .expect("backtrace capture failed")
     }
 
+    pub(crate) unsafe fn attachments(this: Ref<'_, Self>) -> &[Attachment] {
+        &this.deref().attachments
+    }
+
+    unsafe fn attachments_mut(this: Mut<'_, Self>) -> &mut Vec<Attachment> {
+        &mut this.deref_mut().attachments
+    }
+
+    pub(crate) unsafe fn location(this: Ref<'_, Self>) -> Option<&'static Location<'static>> {
+        this.deref().location
+    }
+
+    #[cfg(feature = "futures")]
+    pub(crate) unsafe fn set_location(this: Mut<'_, Self>, location: &'static Location<'static>) {
+        this.deref_mut().location = Some(location);
+    }
+
+    // Finds the location recorded for `error` if it is the object of one of
+    // the layers of this anyhow::Error, rather than some lower level source.
+    pub(crate) unsafe fn location_of(
+        this: Ref<'_, Self>,
+        error: &(dyn StdError + 'static),
+    ) -> Option<&'static Location<'static>> {
+        Self::location(Self::layer_of(this, error)?)
+    }
+
+    // The layer of this anyhow::Error whose object is `error`, if any.
+    pub(crate) unsafe fn layer_of<'a>(
+        this: Ref<'a, Self>,
+        error: &(dyn StdError + 'static),
+    ) -> Option<Ref<'a, Self>> {
+        let target = error as *const dyn StdError as *const ();
+        let mut layer = Some(this);
+        while let Some(current) = layer {
+            let object = Self::error(current) as *const dyn StdError as *const ();
+            if object == target {
+                return Some(current);
+            }
+            layer = Self::inner(current);
+        }
+        None
+    }
+
+    // The Rust type name of one of the errors in this error's chain, if it is
+    // an object that anyhow constructed the chain from, or a remote layer
+    // that carries the type name of the original.
+    #[cfg(any(feature = "std", feature = "serde"))]
+    pub(crate) unsafe fn type_name_of<'a>(
+        this: Ref<'a, Self>,
+        error: &(dyn StdError + 'static),
+    ) -> Option<&'a str> {
+        let mut layer = Some(this);
+        while let Some(current) = layer {
+            if let Some(type_name) = (vtable(current.ptr).object_type_name)(current, error) {
+                return Some(type_name);
+            }
+            layer = Self::inner(current);
+        }
+        None
+    }
+
+    // The report handler that was created along with the innermost anyhow
+    // layer of this error. Context layers do not get one of their own.
+    pub(crate) unsafe fn handler(this: Ref<'_, Self>) -> Option<&dyn ReportHandler> {
+        let mut layer = Some(this);
+        while let Some(current) = layer {
+            if let Some(handler) = &current.deref().handler {
+                return Some(&**handler);
+            }
+            layer = Self::inner(current);
+        }
+        None
+    }
+
+    // The anyhow::Error wrapped by this layer, if this layer was created by
+    // Error::context.
+    pub(crate) unsafe fn inner(this: Ref<'_, Self>) -> Option<Ref<'_, Self>> {
+        (vtable(this.ptr).object_inner)(this)
+    }
+
+    // The aggregate of independent errors held by one of the layers of this
+    // error, if any.
+    pub(crate) unsafe fn errors(this: Ref<'_, Self>) -> Option<&Errors> {
+        let target = TypeId::of::<Errors>();
+        let addr = (vtable(this.ptr).object_downcast_ref)(this, target)?;
+        Some(addr.cast::<Errors>().deref())
+    }
+
+    // Downcast the object of this one layer. Unlike for downcast_ref, nothing
+    // is found in the layers inside of it.
+    #[cfg(feature = "std")]
+    pub(crate) unsafe fn downcast_layer<T>(this: Ref<'_, Self>) -> Option<&T>
+    where
+        T: 'static,
+    {
+        if Self::inner(this).is_some() {
+            return None;
+        }
+        let target = TypeId::of::<T>();
+        let addr = (vtable(this.ptr).object_downcast_ref)(this, target)?;
+        Some(addr.cast::<T>().deref())
+    }
+
     #[cfg(backtrace)]
     unsafe fn provide<'a>(this: Ref<'a, Self>, demand: &mut Demand<'a>) {
         if let Some(backtrace) = &this.deref().backtrace {
@@ -916,8 +2049,8 @@ impl ErrorImpl {
     }
 
     #[cold]
-    pub(crate) unsafe fn chain(this: Ref<Self>) -> Chain {
-        Chain::new(Self::error(this))
+    pub(crate) unsafe fn chain(this: Ref<'_, Self>) -> Chain<'_> {
+        Chain::with_layers(Self::error(this), this)
     }
 }
 
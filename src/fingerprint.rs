@@ -0,0 +1,118 @@
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+use crate::backtrace::filter;
+use crate::error::ErrorImpl;
+use crate::ptr::Ref;
+use crate::{ConditionFailed, FormattedMessage};
+use core::fmt::{self, Write};
+
+// 64-bit FNV-1a. The hashers of the standard library are not guaranteed to
+// produce the same values from one release to the next.
+struct Hasher(u64);
+
+impl Hasher {
+    fn new() -> Self {
+        Hasher(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    // Every part is terminated by a byte that does not occur in UTF-8, so
+    // that adjacent parts cannot run together.
+    fn end_part(&mut self) {
+        self.write(&[0xff]);
+    }
+
+    fn part(&mut self, part: &str) {
+        self.write(part.as_bytes());
+        self.end_part();
+    }
+}
+
+impl Write for Hasher {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write(s.as_bytes());
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn fingerprint(this: Ref<'_, ErrorImpl>, frames: usize) -> u64 {
+    let mut hasher = Hasher::new();
+
+    for error in ErrorImpl::chain(this) {
+        let type_name = ErrorImpl::type_name_of(this, error);
+        hasher.part(type_name.unwrap_or("?"));
+
+        let layer = ErrorImpl::layer_of(this, error);
+        let formatted =
+            layer.and_then(|layer| ErrorImpl::downcast_layer::<FormattedMessage>(layer));
+        if let Some(formatted) = formatted {
+            hasher.part(formatted.template());
+        } else if type_name == Some("&str") {
+            // A message or context that is a &'static str is the same on
+            // every occurrence.
+            let _ = write!(hasher, "{}", error);
+            hasher.end_part();
+        } else if let Some(condition) =
+            layer.and_then(|layer| ErrorImpl::downcast_layer::<ConditionFailed>(layer))
+        {
+            hasher.part(condition.expression());
+        } else {
+            hasher.end_part();
+        }
+    }
+
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    hash_frames(&mut hasher, this, frames);
+    #[cfg(not(any(std_backtrace, feature = "backtrace")))]
+    let _ = frames;
+
+    hasher.0
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+unsafe fn hash_frames(hasher: &mut Hasher, this: Ref<'_, ErrorImpl>, frames: usize) {
+    const STD_PREFIXES: &[&str] = &[
+        "std::",
+        "core::",
+        "alloc::",
+        "<std::",
+        "<core::",
+        "<alloc::",
+        "__rust",
+        "rust_begin_unwind",
+    ];
+
+    if frames == 0 {
+        return;
+    }
+
+    let mut remaining = frames;
+    for frame in crate::BacktraceFrames::new(ErrorImpl::backtrace(this)) {
+        let function = match frame.name() {
+            Some(function) => function,
+            None => continue,
+        };
+        if filter::is_runtime(function) {
+            break;
+        }
+        let file = frame.file().and_then(|file| file.to_str());
+        if filter::is_capture(function)
+            || STD_PREFIXES
+                .iter()
+                .any(|prefix| function.starts_with(prefix))
+            || filter::is_hidden(function, file)
+        {
+            continue;
+        }
+        hasher.part(function);
+        remaining -= 1;
+        if remaining == 0 {
+            break;
+        }
+    }
+}
@@ -1,16 +1,25 @@
 
+use crate::attachment::AttachmentsState;
 use crate::chain::Chain;
 use crate::error::ErrorImpl;
 use crate::ptr::Ref;
-use core::fmt::{self, Debug, Write};
+use crate::Error;
+use core::fmt::{self, Debug, Display, Write};
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+use crate::backtrace::text::{frame_number, location_file, symbol_name};
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "std")]
+use std::env;
 
 impl ErrorImpl {
     pub(crate) unsafe fn display(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", Self::error(this))?;
+        write!(f, "{}", Message(Self::error(this)))?;
 
         if f.alternate() {
             for cause in Self::chain(this).skip(1) {
-                write!(f, ": {}", cause)?;
+                write!(f, ": {}", Message(cause))?;
             }
         }
 
@@ -21,26 +30,18 @@ impl ErrorImpl {
         let error = Self::error(this);
 
         if f.alternate() {
+            #[cfg(feature = "std")]
+            {
+                if crate::redact::is_redacting() {
+                    return crate::redact::write(f, format_args!("{:#?}", error));
+                }
+            }
             return Debug::fmt(error, f);
         }
 
-        write!(f, "{}", error)?;
-
-        if let Some(cause) = error.source() {
-            write!(f, "\n\nCaused by:")?;
-            let multiple = cause.source().is_some();
-            for (n, error) in Chain::new(cause).enumerate() {
-                writeln!(f)?;
-                let mut indented = Indented {
-                    inner: f,
-                    number: if multiple { Some(n) } else { None },
-                    started: false,
-                };
-                write!(indented, "{}", error)?;
-            }
-        }
+        Self::summary(this, f)?;
 
-        #[cfg(any(backtrace, feature = "backtrace"))]
+        #[cfg(any(std_backtrace, feature = "backtrace"))]
         {
             use crate::backtrace::BacktraceStatus;
 
@@ -57,7 +58,96 @@ impl ErrorImpl {
                     writeln!(f, "Stack backtrace:")?;
                 }
                 backtrace.truncate(backtrace.trim_end().len());
-                write!(f, "{}", backtrace)?;
+                write_backtrace(f, &filter_frames(&backtrace))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Everything in the Debug representation other than the backtrace.
+    unsafe fn summary(this: Ref<Self>, f: &mut fmt::Formatter) -> fmt::Result {
+        let error = Self::error(this);
+        let color = render_color();
+
+        if color {
+            write!(f, "{}{}{}", HEADLINE, Message(error), RESET)?;
+        } else {
+            write!(f, "{}", Message(error))?;
+        }
+
+        let locations = render_locations();
+        if locations {
+            if let Some(location) = Self::location(this) {
+                write!(f, "\n    at {}", location)?;
+            }
+        }
+
+        if let Some(cause) = error.source() {
+            write!(f, "\n\nCaused by:")?;
+            let multiple = cause.source().is_some();
+            for (n, error) in Chain::new(cause).enumerate() {
+                writeln!(f)?;
+                let mut indented = Indented {
+                    inner: f,
+                    number: if multiple { Some(n) } else { None },
+                    started: false,
+                    color,
+                };
+                write!(indented, "{}", Message(error))?;
+                if locations {
+                    if let Some(location) = Self::location_of(this, error) {
+                        write!(indented, "\nat {}", location)?;
+                    }
+                }
+            }
+        }
+
+        #[cfg(feature = "std")]
+        {
+            if let Some(snapshot) = crate::scope::innermost(this) {
+                write!(f, "\n\nWhile:")?;
+                for frame in snapshot.frames() {
+                    writeln!(f)?;
+                    let mut indented = Indented {
+                        inner: f,
+                        number: None,
+                        started: false,
+                        color,
+                    };
+                    write!(indented, "{}", Message(frame))?;
+                }
+            }
+        }
+
+        if let Some(errors) = Self::errors(this) {
+            write!(f, "\n\nErrors:")?;
+            for (n, error) in errors.iter().enumerate() {
+                writeln!(f)?;
+                let mut indented = Indented {
+                    inner: f,
+                    number: Some(n),
+                    started: false,
+                    color,
+                };
+                write!(indented, "{}", Summary(error))?;
+            }
+        }
+
+        let mut attachments = AttachmentsState::new(this)
+            .filter(|attachment| attachment.is_printable())
+            .peekable();
+        if attachments.peek().is_some() {
+            write!(f, "\n\nAttachments:")?;
+            for attachment in attachments {
+                writeln!(f)?;
+                let mut indented = Indented {
+                    inner: f,
+                    number: None,
+                    started: false,
+                    color,
+                };
+                write!(indented, "{}", Message(&attachment))?;
             }
         }
 
@@ -65,10 +155,242 @@ impl ErrorImpl {
     }
 }
 
+// One message of the report. While the error is rendered by Error::redacted,
+// the registered redactors are applied to it.
+struct Message<'a, T: ?Sized>(&'a T);
+
+impl<T> Display for Message<'_, T>
+where
+    T: ?Sized + Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            if crate::redact::is_redacting() {
+                return crate::redact::write(f, format_args!("{}", self.0));
+            }
+        }
+        Display::fmt(self.0, f)
+    }
+}
+
+// Renders one of several independent errors as a nested subtree of the report.
+struct Summary<'a>(&'a Error);
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::summary(self.0.inner.by_ref(), f) }
+    }
+}
+
+// Source locations are shown in the Debug representation only if opted into
+// by setting ANYHOW_LOCATION=1.
+#[cfg(feature = "std")]
+fn render_locations() -> bool {
+    static ENABLED: AtomicUsize = AtomicUsize::new(0);
+    match ENABLED.load(Ordering::Relaxed) {
+        0 => {}
+        1 => return false,
+        _ => return true,
+    }
+    let enabled = match env::var_os("ANYHOW_LOCATION") {
+        Some(s) => s != "0",
+        None => false,
+    };
+    ENABLED.store(enabled as usize + 1, Ordering::Relaxed);
+    enabled
+}
+
+#[cfg(not(feature = "std"))]
+fn render_locations() -> bool {
+    false
+}
+
+// Escape sequences used when the report is rendered in color.
+const HEADLINE: &str = "\x1b[1;31m";
+const NUMBER: &str = "\x1b[33m";
+#[cfg(all(feature = "color", any(std_backtrace, feature = "backtrace")))]
+const STD_FRAME: &str = "\x1b[2m";
+#[cfg(all(feature = "color", any(std_backtrace, feature = "backtrace")))]
+const CRATE_FRAME: &str = "\x1b[1;32m";
+const RESET: &str = "\x1b[0m";
+
+// With the "color" feature, the Debug representation is styled with ANSI
+// escape sequences if stderr is a terminal. NO_COLOR=1 turns color off and
+// CLICOLOR_FORCE=1 turns it on regardless of the terminal.
+#[cfg(feature = "color")]
+fn render_color() -> bool {
+    static ENABLED: AtomicUsize = AtomicUsize::new(0);
+    match ENABLED.load(Ordering::Relaxed) {
+        0 => {}
+        1 => return false,
+        _ => return true,
+    }
+    let enabled = match (env::var_os("NO_COLOR"), env::var_os("CLICOLOR_FORCE")) {
+        (Some(no_color), _) if !no_color.is_empty() => false,
+        (_, Some(force)) if force != "0" => true,
+        _ => stderr_is_terminal(),
+    };
+    ENABLED.store(enabled as usize + 1, Ordering::Relaxed);
+    enabled
+}
+
+#[cfg(not(feature = "color"))]
+fn render_color() -> bool {
+    false
+}
+
+#[cfg(all(feature = "color", not(anyhow_no_is_terminal)))]
+#[allow(clippy::incompatible_msrv)]
+fn stderr_is_terminal() -> bool {
+    use std::io::IsTerminal;
+    std::io::stderr().is_terminal()
+}
+
+#[cfg(all(feature = "color", anyhow_no_is_terminal))]
+fn stderr_is_terminal() -> bool {
+    false
+}
+
+#[cfg(all(feature = "color", any(std_backtrace, feature = "backtrace")))]
+fn write_backtrace(f: &mut fmt::Formatter, backtrace: &str) -> fmt::Result {
+    if !render_color() {
+        return f.write_str(backtrace);
+    }
+
+    let lines: Vec<&str> = backtrace.split('\n').collect();
+    let mut i = 0;
+    while i < lines.len() {
+        if i > 0 {
+            f.write_char('\n')?;
+        }
+        let symbol = lines[i];
+        let location = match lines.get(i + 1) {
+            Some(next) if next.trim_start().starts_with("at ") => Some(*next),
+            _ => None,
+        };
+        match frame_style(symbol, location) {
+            Some(style) => write!(f, "{}{}{}", style, symbol, RESET)?,
+            None => f.write_str(symbol)?,
+        }
+        if let Some(location) = location {
+            match frame_style(symbol, Some(location)) {
+                Some(style) => write!(f, "\n{}{}{}", style, location, RESET)?,
+                None => write!(f, "\n{}", location)?,
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+    Ok(())
+}
+
+#[cfg(all(not(feature = "color"), any(std_backtrace, feature = "backtrace")))]
+fn write_backtrace(f: &mut fmt::Formatter, backtrace: &str) -> fmt::Result {
+    f.write_str(backtrace)
+}
+
+// Frames of the standard library are dimmed. Frames whose source file is
+// under the current directory, which is where the crate being run lives, are
+// highlighted.
+#[cfg(all(feature = "color", any(std_backtrace, feature = "backtrace")))]
+fn frame_style(symbol: &str, location: Option<&str>) -> Option<&'static str> {
+    const STD_PREFIXES: &[&str] = &[
+        "std::", "core::", "alloc::", "test::", "<std::", "<core::", "<alloc::", "<test::",
+        "__rust", "rust_begin_unwind", "<unknown>",
+    ];
+
+    let function = symbol_name(symbol);
+    let file = location.and_then(location_file);
+
+    let is_std = STD_PREFIXES.iter().any(|prefix| function.starts_with(prefix))
+        || file.map_or(false, |file| {
+            file.starts_with("/rustc/") || file.contains("/rustlib/src/rust/library/")
+        });
+    let is_crate = file.map_or(false, |file| {
+        file.starts_with("./") || file.starts_with(".\\")
+    });
+
+    if is_std {
+        Some(STD_FRAME)
+    } else if is_crate {
+        Some(CRATE_FRAME)
+    } else {
+        None
+    }
+}
+
+// Removes uninteresting frames from a rendered backtrace, and notes how many
+// were removed at the end so that nothing disappears without a trace.
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+fn filter_frames(backtrace: &str) -> String {
+    use crate::backtrace::filter;
+
+    // Each numbered frame is followed by the lines of its location and of any
+    // frames that were inlined into it.
+    let mut header = Vec::new();
+    let mut frames: Vec<Vec<&str>> = Vec::new();
+    for line in backtrace.split('\n') {
+        if frame_number(line).is_some() {
+            frames.push(vec![line]);
+        } else if let Some(frame) = frames.last_mut() {
+            frame.push(line);
+        } else {
+            header.push(line);
+        }
+    }
+
+    let start = frames
+        .iter()
+        .take_while(|frame| frame_symbols(frame).all(|(function, _)| filter::is_capture(function)))
+        .count();
+    let end = frames[start..]
+        .iter()
+        .position(|frame| frame_symbols(frame).any(|(function, _)| filter::is_runtime(function)))
+        .map_or(frames.len(), |end| start + end);
+
+    let mut lines = header;
+    let mut hidden = frames.len() - (end - start);
+    for frame in &frames[start..end] {
+        if frame_symbols(frame).all(|(function, file)| filter::is_hidden(function, file)) {
+            hidden += 1;
+        } else {
+            lines.extend(frame);
+        }
+    }
+
+    let mut filtered = lines.join("\n");
+    match hidden {
+        0 => {}
+        1 => filtered.push_str("\n      1 frame hidden"),
+        n => filtered.push_str(&format!("\n      {} frames hidden", n)),
+    }
+    filtered
+}
+
+// The function name and source file of each symbol in one frame of a rendered
+// backtrace.
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+fn frame_symbols<'a>(frame: &'a [&'a str]) -> impl Iterator<Item = (&'a str, Option<&'a str>)> {
+    let mut lines = frame.iter().peekable();
+    core::iter::from_fn(move || {
+        let function = symbol_name(lines.next()?);
+        let file = match lines.peek() {
+            Some(line) => location_file(line),
+            None => None,
+        };
+        if file.is_some() {
+            lines.next();
+        }
+        Some((function, file))
+    })
+}
+
 struct Indented<'a, D> {
     inner: &'a mut D,
     number: Option<usize>,
     started: bool,
+    color: bool,
 }
 
 impl<T> Write for Indented<'_, T>
@@ -80,6 +402,9 @@ where
             if !self.started {
                 self.started = true;
                 match self.number {
+                    Some(number) if self.color => {
+                        write!(self.inner, "{}{: >5}:{} ", NUMBER, number, RESET)?;
+                    }
                     Some(number) => write!(self.inner, "{: >5}: ", number)?,
                     None => self.inner.write_str("    ")?,
                 }
@@ -113,6 +438,7 @@ mod tests {
             inner: &mut output,
             number: Some(2),
             started: false,
+            color: false,
         }
         .write_str(input)
         .unwrap();
@@ -130,6 +456,7 @@ mod tests {
             inner: &mut output,
             number: Some(12),
             started: false,
+            color: false,
         }
         .write_str(input)
         .unwrap();
@@ -147,6 +474,7 @@ mod tests {
             inner: &mut output,
             number: None,
             started: false,
+            color: false,
         }
         .write_str(input)
         .unwrap();
@@ -0,0 +1,265 @@
+use crate::ensure::format_truncated;
+use crate::FormattedMessage;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::mem;
+
+// Recorded values are cut off at this many bytes, so that an error about a
+// huge value does not carry a second copy of it.
+const ARG_LIMIT: usize = 256;
+
+impl FormattedMessage {
+    /// The message with the arguments interpolated into it, which is what the
+    /// error displays.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The format string literal that was passed to the macro.
+    pub fn template(&self) -> &'static str {
+        self.template
+    }
+
+    /// The values of the positional arguments, in order.
+    ///
+    /// Values longer than 256 bytes are truncated and end with `…`. An
+    /// argument that implements neither `Debug` nor `Display` is recorded as
+    /// `<unformattable>`.
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    /// The names and values of the named arguments, in order, followed by
+    /// those of the arguments that the format string captures implicitly.
+    ///
+    /// Values longer than 256 bytes are truncated and end with `…`.
+    pub fn named_args(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        self.named_args
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+    }
+
+    /// The value of the named argument `name`, if there is one.
+    pub fn named_arg(&self, name: &str) -> Option<&str> {
+        self.named_args
+            .iter()
+            .find(|(arg, _value)| *arg == name)
+            .map(|(_arg, value)| value.as_str())
+    }
+}
+
+impl Display for FormattedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        #[cfg(feature = "std")]
+        {
+            if !self.sensitive.is_empty() && crate::redact::is_redacting() {
+                return crate::redact::substitute(f, &self.message, self.sensitive.clone());
+            }
+        }
+        f.write_str(&self.message)
+    }
+}
+
+impl Debug for FormattedMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // The message as displayed, so that Sensitive values in it stay
+        // hidden in a redacted report.
+        struct Message<'a>(&'a FormattedMessage);
+
+        impl Debug for Message<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                Debug::fmt(&self.0.to_string(), f)
+            }
+        }
+
+        f.debug_struct("FormattedMessage")
+            .field("message", &Message(self))
+            .field("template", &self.template)
+            .field("args", &self.args)
+            .field("named_args", &self.named_args)
+            .finish()
+    }
+}
+
+// Records one argument of anyhow!($fmt, $($arg)*) by its Debug representation
+// if it has one, otherwise by its Display representation. An argument that
+// implements neither, because the format string only uses it as `{:x}`, `{:p}`
+// or the like, is recorded as "<unformattable>". Sensitive arguments are
+// recorded as "<redacted>".
+
+#[doc(hidden)]
+pub trait DebugArg {
+    fn __format_arg(self) -> String;
+}
+
+impl<T> DebugArg for (T,)
+where
+    T: Debug,
+{
+    fn __format_arg(self) -> String {
+        #[cfg(feature = "std")]
+        return crate::redact::redacting(|| {
+            format_truncated(format_args!("{:?}", self.0), ARG_LIMIT)
+        });
+        #[cfg(not(feature = "std"))]
+        return format_truncated(format_args!("{:?}", self.0), ARG_LIMIT);
+    }
+}
+
+#[doc(hidden)]
+pub trait DisplayArg {
+    fn __format_arg(self) -> String;
+}
+
+impl<T> DisplayArg for &(T,)
+where
+    T: Display,
+{
+    fn __format_arg(self) -> String {
+        #[cfg(feature = "std")]
+        return crate::redact::redacting(|| {
+            format_truncated(format_args!("{}", self.0), ARG_LIMIT)
+        });
+        #[cfg(not(feature = "std"))]
+        return format_truncated(format_args!("{}", self.0), ARG_LIMIT);
+    }
+}
+
+#[doc(hidden)]
+pub trait OpaqueArg {
+    fn __format_arg(self) -> String;
+}
+
+impl<T> OpaqueArg for &mut (T,) {
+    fn __format_arg(self) -> String {
+        String::from("<unformattable>")
+    }
+}
+
+// A piece of a format string: either text that appears in the message as is,
+// or a placeholder, with the name of the argument if the format string
+// captures it implicitly.
+enum Piece {
+    Literal(String),
+    Placeholder(Option<&'static str>),
+}
+
+// The arguments that the format string captures implicitly, like `path` in
+// anyhow!("failed to open {path}"), are not visible to the macro. Their values
+// are recovered from the message as the text that each placeholder produced,
+// wherever the text around the placeholder determines where that starts and
+// ends. Sensitive values are recorded as "<redacted>".
+pub(crate) fn implicit_args(
+    template: &'static str,
+    message: &str,
+    named: &[(&'static str, String)],
+    sensitive: &[(usize, usize)],
+) -> Vec<(&'static str, String)> {
+    let mut implicit = Vec::new();
+    let pieces = match parse(template, named) {
+        Some(pieces) => pieces,
+        None => return implicit,
+    };
+
+    let mut pos = 0;
+    let mut pieces = pieces.iter().peekable();
+    while let Some(piece) = pieces.next() {
+        match piece {
+            Piece::Literal(literal) => {
+                if !message[pos..].starts_with(literal.as_str()) {
+                    break;
+                }
+                pos += literal.len();
+            }
+            Piece::Placeholder(name) => {
+                let end = match pieces.peek() {
+                    None => message.len(),
+                    Some(Piece::Literal(literal)) => match find_unique(&message[pos..], literal) {
+                        Some(offset) => pos + offset,
+                        None => break,
+                    },
+                    Some(Piece::Placeholder(_)) => break,
+                };
+                if let Some(name) = *name {
+                    if !implicit.iter().any(|(arg, _value)| *arg == name) {
+                        let value = if sensitive
+                            .iter()
+                            .any(|&(start, stop)| start < end && pos < stop)
+                        {
+                            String::from("<redacted>")
+                        } else {
+                            format_truncated(format_args!("{}", &message[pos..end]), ARG_LIMIT)
+                        };
+                        implicit.push((name, value));
+                    }
+                }
+                pos = end;
+            }
+        }
+    }
+    implicit
+}
+
+// Splits a format string into pieces, or returns None if it does not capture
+// any argument implicitly.
+fn parse(template: &'static str, named: &[(&'static str, String)]) -> Option<Vec<Piece>> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut captures = false;
+    let mut rest = template;
+    while let Some(index) = rest.find(|ch| ch == '{' || ch == '}') {
+        literal.push_str(&rest[..index]);
+        let brace = rest.as_bytes()[index];
+        let after = &rest[index + 1..];
+        if after.as_bytes().first() == Some(&brace) {
+            // {{ or }}
+            literal.push(brace as char);
+            rest = &after[1..];
+            continue;
+        }
+        let end = after.find('}')?;
+        let arg = after[..end].split(':').next().unwrap_or("").trim();
+        let name = if is_identifier(arg) && !named.iter().any(|(name, _value)| *name == arg) {
+            captures = true;
+            Some(arg)
+        } else {
+            None
+        };
+        if !literal.is_empty() {
+            pieces.push(Piece::Literal(mem::replace(&mut literal, String::new())));
+        }
+        pieces.push(Piece::Placeholder(name));
+        rest = &after[end + 1..];
+    }
+    if !captures {
+        return None;
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Some(pieces)
+}
+
+fn is_identifier(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some(first) if first.is_alphabetic() || first == '_' => {
+            arg != "_" && chars.all(|ch| ch.is_alphanumeric() || ch == '_')
+        }
+        _ => false,
+    }
+}
+
+// The offset of the only occurrence of `literal` in `text`, counting ones that
+// overlap.
+fn find_unique(text: &str, literal: &str) -> Option<usize> {
+    let first = text.find(literal)?;
+    let next = first + text[first..].chars().next().map_or(1, char::len_utf8);
+    if text[next..].contains(literal) {
+        None
+    } else {
+        Some(first)
+    }
+}
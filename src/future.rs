@@ -0,0 +1,229 @@
+use crate::context::ext::StdError;
+use crate::error::ErrorImpl;
+use crate::{
+    ContextFuture, ContextStream, Error, FutureContext, StreamContext, WithContextFuture,
+    WithContextStream,
+};
+use core::fmt::Display;
+use core::future::Future;
+use core::panic::Location;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::Stream;
+
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+fn caller() -> Option<&'static Location<'static>> {
+    #[cfg(not(anyhow_no_track_caller))]
+    #[allow(clippy::incompatible_msrv)]
+    return Some(Location::caller());
+    #[cfg(anyhow_no_track_caller)]
+    return None;
+}
+
+// The error is created inside of poll, so the location captured by
+// #[track_caller] would point into this module. Replace it with the location
+// at which the combinator was called.
+fn relocate(mut error: Error, location: Option<&'static Location<'static>>) -> Error {
+    if let Some(location) = location {
+        unsafe { ErrorImpl::set_location(error.inner.by_mut(), location) }
+    }
+    error
+}
+
+impl<Fut, T, E> FutureContext<T, E> for Fut
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context<C>(self, context: C) -> ContextFuture<Self, C>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        ContextFuture {
+            future: self,
+            context: Some(context),
+            location: caller(),
+        }
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn with_context<C, F>(self, f: F) -> WithContextFuture<Self, F>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        WithContextFuture {
+            future: self,
+            f: Some(f),
+            location: caller(),
+        }
+    }
+}
+
+impl<S, T, E> StreamContext<T, E> for S
+where
+    S: Stream<Item = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context<C>(self, context: C) -> ContextStream<Self, C>
+    where
+        C: Display + Clone + Send + Sync + 'static,
+    {
+        ContextStream {
+            stream: self,
+            context,
+            location: caller(),
+        }
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn with_context<C, F>(self, f: F) -> WithContextStream<Self, F>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnMut() -> C,
+    {
+        WithContextStream {
+            stream: self,
+            f,
+            location: caller(),
+        }
+    }
+}
+
+// The inner future or stream is the only structurally pinned field. The
+// context and the closure are never pinned, so they may be moved out.
+impl<Fut: Unpin, C> Unpin for ContextFuture<Fut, C> {}
+impl<Fut: Unpin, F> Unpin for WithContextFuture<Fut, F> {}
+impl<S: Unpin, C> Unpin for ContextStream<S, C> {}
+impl<S: Unpin, F> Unpin for WithContextStream<S, F> {}
+
+impl<Fut, T, E, C> Future for ContextFuture<Fut, C>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+    C: Display + Send + Sync + 'static,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        // Not using map_err to save useless frames off the captured backtrace
+        // in ext_context.
+        match future.poll(cx) {
+            Poll::Ready(Ok(ok)) => Poll::Ready(Ok(ok)),
+            Poll::Ready(Err(error)) => {
+                let context = this.context.take().expect("future polled after completion");
+                Poll::Ready(Err(relocate(error.ext_context(context), this.location)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<Fut, T, E, C, F> Future for WithContextFuture<Fut, F>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+    C: Display + Send + Sync + 'static,
+    F: FnOnce() -> C,
+{
+    type Output = Result<T, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+        match future.poll(cx) {
+            Poll::Ready(Ok(ok)) => Poll::Ready(Ok(ok)),
+            Poll::Ready(Err(error)) => {
+                let f = this.f.take().expect("future polled after completion");
+                Poll::Ready(Err(relocate(error.ext_context(f()), this.location)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S, T, E, C> Stream for ContextStream<S, C>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+    C: Display + Clone + Send + Sync + 'static,
+{
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(Ok(ok))) => Poll::Ready(Some(Ok(ok))),
+            Poll::Ready(Some(Err(error))) => {
+                let context = this.context.clone();
+                Poll::Ready(Some(Err(relocate(
+                    error.ext_context(context),
+                    this.location,
+                ))))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+impl<S, T, E, C, F> Stream for WithContextStream<S, F>
+where
+    S: Stream<Item = Result<T, E>>,
+    E: StdError + Send + Sync + 'static,
+    C: Display + Send + Sync + 'static,
+    F: FnMut() -> C,
+{
+    type Item = Result<T, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let stream = unsafe { Pin::new_unchecked(&mut this.stream) };
+        match stream.poll_next(cx) {
+            Poll::Ready(Some(Ok(ok))) => Poll::Ready(Some(Ok(ok))),
+            Poll::Ready(Some(Err(error))) => {
+                let context = (this.f)();
+                Poll::Ready(Some(Err(relocate(
+                    error.ext_context(context),
+                    this.location,
+                ))))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+pub(crate) mod private {
+    use super::*;
+
+    pub trait SealedFuture {}
+    pub trait SealedStream {}
+
+    impl<Fut, T, E> SealedFuture for Fut
+    where
+        Fut: Future<Output = Result<T, E>>,
+        E: StdError,
+    {
+    }
+
+    impl<S, T, E> SealedStream for S
+    where
+        S: Stream<Item = Result<T, E>>,
+        E: StdError,
+    {
+    }
+}
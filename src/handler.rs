@@ -0,0 +1,89 @@
+use crate::error::ErrorImpl;
+use crate::{DefaultHandler, Error, ReportHandler, StdError};
+use alloc::boxed::Box;
+use core::any::{Any, TypeId};
+use core::fmt;
+
+#[cfg(feature = "std")]
+use crate::InstallError;
+#[cfg(feature = "std")]
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use core::ptr;
+#[cfg(feature = "std")]
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+#[cfg(feature = "std")]
+type Hook = dyn Fn(&(dyn StdError + 'static)) -> Box<dyn ReportHandler> + Send + Sync;
+
+// Double boxed so that the pointer to the installed hook is thin. Once
+// installed, the hook is never freed.
+#[cfg(feature = "std")]
+static HOOK: AtomicPtr<Box<Hook>> = AtomicPtr::new(ptr::null_mut());
+
+#[cfg(feature = "std")]
+pub(crate) fn install(hook: Box<Hook>) -> Result<(), InstallError> {
+    let hook = Box::into_raw(Box::new(hook));
+    match HOOK.compare_exchange(ptr::null_mut(), hook, Ordering::AcqRel, Ordering::Acquire) {
+        Ok(_) => Ok(()),
+        Err(_) => {
+            drop(unsafe { Box::from_raw(hook) });
+            Err(InstallError)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn capture(error: &(dyn StdError + 'static)) -> Option<Box<dyn ReportHandler>> {
+    let hook = HOOK.load(Ordering::Acquire);
+    if hook.is_null() {
+        None
+    } else {
+        Some(unsafe { (*hook)(error) })
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn capture(error: &(dyn StdError + 'static)) -> Option<Box<dyn ReportHandler>> {
+    let _ = error;
+    None
+}
+
+impl dyn ReportHandler {
+    /// Returns true if the handler is of type `T`.
+    pub fn is<T>(&self) -> bool
+    where
+        T: ReportHandler,
+    {
+        Any::type_id(self) == TypeId::of::<T>()
+    }
+
+    /// Downcast the handler to its concrete type, for example to read the
+    /// state that it captured when its error was created.
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: ReportHandler,
+    {
+        if self.is::<T>() {
+            Some(unsafe { &*(self as *const dyn ReportHandler as *const T) })
+        } else {
+            None
+        }
+    }
+}
+
+impl ReportHandler for DefaultHandler {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug(error.inner.by_ref(), f) }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a report handler has already been installed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl StdError for InstallError {}
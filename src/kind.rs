@@ -64,6 +64,7 @@ impl<T> AdhocKind for &T where T: ?Sized + Display + Debug + Send + Sync + 'stat
 
 impl Adhoc {
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn new<M>(self, message: M) -> Error
     where
         M: Display + Debug + Send + Sync + 'static,
@@ -81,15 +82,18 @@ pub trait TraitKind: Sized {
     }
 }
 
-impl<E> TraitKind for E where E: Into<Error> {}
+impl<E> TraitKind for E where Error: From<E> {}
 
 impl Trait {
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn new<E>(self, error: E) -> Error
     where
-        E: Into<Error>,
+        Error: From<E>,
     {
-        error.into()
+        // Calling From directly rather than Into so that the caller's location
+        // is the one recorded by #[track_caller].
+        Error::from(error)
     }
 }
 
@@ -110,6 +114,7 @@ impl BoxedKind for Box<dyn StdError + Send + Sync> {}
 #[cfg(feature = "std")]
 impl Boxed {
     #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
     pub fn new(self, error: Box<dyn StdError + Send + Sync>) -> Error {
         let backtrace = backtrace_if_absent!(&*error);
         Error::from_boxed(error, backtrace)
@@ -0,0 +1,143 @@
+#[cfg(feature = "std")]
+use crate::StdError;
+use crate::{Error, Kinded, KindedContext};
+use core::fmt::{self, Debug, Display};
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+// The kind is stored as an attachment of the error. The wrapper keeps it
+// apart from any value of type K that the user attaches themselves.
+struct Kind<K>(K);
+
+impl<K> Kinded<K>
+where
+    K: Copy + Send + Sync + 'static,
+{
+    /// Classify an error with the given kind.
+    ///
+    /// If the error already had a kind of type `K`, the new one replaces it.
+    #[cold]
+    #[must_use]
+    pub fn new(kind: K, error: Error) -> Self {
+        Kinded {
+            error: error.attach(Kind(kind)),
+            kind: PhantomData,
+        }
+    }
+
+    /// The kind of this error.
+    pub fn kind(&self) -> K {
+        // Every constructor attaches a kind.
+        self.error.attachment::<Kind<K>>().unwrap().0
+    }
+
+    /// Wrap the error value with additional context, keeping its kind.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        Kinded {
+            error: self.error.context(context),
+            kind: PhantomData,
+        }
+    }
+
+    /// The `anyhow::Error`, which still carries the kind.
+    pub fn into_error(self) -> Error {
+        self.error
+    }
+}
+
+impl<K> Deref for Kinded<K> {
+    type Target = Error;
+
+    fn deref(&self) -> &Error {
+        &self.error
+    }
+}
+
+impl<K> From<Error> for Kinded<K>
+where
+    K: Copy + Default + Send + Sync + 'static,
+{
+    fn from(error: Error) -> Self {
+        if error.attachment::<Kind<K>>().is_some() {
+            Kinded {
+                error,
+                kind: PhantomData,
+            }
+        } else {
+            Kinded::new(K::default(), error)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, E> From<E> for Kinded<K>
+where
+    K: Copy + Default + Send + Sync + 'static,
+    E: StdError + Send + Sync + 'static,
+{
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(error: E) -> Self {
+        Kinded::new(K::default(), Error::from(error))
+    }
+}
+
+impl<K> From<Kinded<K>> for Error {
+    fn from(kinded: Kinded<K>) -> Self {
+        kinded.error
+    }
+}
+
+impl<T, K> KindedContext<T, K> for Result<T, Kinded<K>>
+where
+    K: Copy + Send + Sync + 'static,
+{
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn context<C>(self, context: C) -> Result<T, Kinded<K>>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.context(context)),
+        }
+    }
+
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn with_context<C, F>(self, context: F) -> Result<T, Kinded<K>>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.context(context())),
+        }
+    }
+}
+
+impl<K> Debug for Kinded<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.error, f)
+    }
+}
+
+impl<K> Display for Kinded<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+pub(crate) mod private {
+    use super::*;
+
+    pub trait Sealed {}
+
+    impl<T, K> Sealed for Result<T, Kinded<K>> {}
+}
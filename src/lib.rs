@@ -128,11 +128,10 @@
 //!   # ;
 //!   ```
 //!
-//! - If using the nightly channel, or stable with `features = ["backtrace"]`, a
-//!   backtrace is captured and printed with the error if the underlying error
-//!   type does not already provide its own. In order to see backtraces, they
-//!   must be enabled through the environment variables described in
-//!   [`std::backtrace`]:
+//! - If using Rust 1.65 or newer, or an older compiler with
+//!   `features = ["backtrace"]`, a backtrace is captured and printed with the
+//!   error. In order to see backtraces, they must be enabled through the
+//!   environment variables described in [`std::backtrace`]:
 //!
 //!   - If you want panics and errors to both have backtraces, set
 //!     `RUST_BACKTRACE=1`;
@@ -140,10 +139,15 @@
 //!   - If you want only panics to have backtraces, set `RUST_BACKTRACE=1` and
 //!     `RUST_LIB_BACKTRACE=0`.
 //!
-//!   The tracking issue for this feature is [rust-lang/rust#53487].
+//!   The program can also decide for itself, at any time, with
+//!   `anyhow::set_backtrace_policy` and `anyhow::with_backtrace_policy`.
+//!
+//!   On the nightly channel, no backtrace is captured if the underlying error
+//!   type already provides its own. The tracking issue for this feature is
+//!   [rust-lang/rust#99301].
 //!
 //!   [`std::backtrace`]: https://doc.rust-lang.org/std/backtrace/index.html#environment-variables
-//!   [rust-lang/rust#53487]: https://github.com/rust-lang/rust/issues/53487
+//!   [rust-lang/rust#99301]: https://github.com/rust-lang/rust/issues/99301
 //!
 //! - Anyhow works with any error type that has an impl of `std::error::Error`,
 //!   including ones defined in your crate. We do not bundle a `derive(Error)`
@@ -192,6 +196,20 @@
 //!   # }
 //!   ```
 //!
+//!   And `require!` unwraps an `Option` or `Result` in place, returning early
+//!   with the given message if there is no value.
+//!
+//!   ```
+//!   # use anyhow::{require, Result};
+//!   # use std::collections::HashMap;
+//!   #
+//!   # fn demo(attributes: HashMap<&str, &str>) -> Result<()> {
+//!   let value = require!(attributes.get("name"), "Missing attribute: name");
+//!   #     let _ = value;
+//!   #     Ok(())
+//!   # }
+//!   ```
+//!
 //! <br>
 //!
 //! # No-std support
@@ -215,6 +233,8 @@
 #![cfg_attr(doc_cfg, feature(doc_cfg))]
 #![cfg_attr(not(feature = "std"), no_std)]
 #![deny(dead_code, unused_imports, unused_mut)]
+// std::backtrace is only used when build.rs finds a compiler that has it.
+#![cfg_attr(std_backtrace, allow(clippy::incompatible_msrv))]
 #![allow(
     clippy::doc_markdown,
     clippy::enum_glob_use,
@@ -239,14 +259,40 @@ extern crate alloc;
 
 #[macro_use]
 mod backtrace;
+mod aggregate;
+mod attachment;
 mod chain;
 mod context;
 mod ensure;
 mod error;
+#[cfg(feature = "std")]
+mod fingerprint;
 mod fmt;
+mod formatted;
+#[cfg(feature = "futures")]
+mod future;
+mod handler;
 mod kind;
+mod kinded;
+#[cfg(feature = "std")]
+mod local;
 mod macros;
+#[cfg(feature = "std")]
+mod panic;
 mod ptr;
+#[cfg(feature = "std")]
+mod redact;
+#[cfg(feature = "serde")]
+mod remote;
+#[cfg(feature = "serde")]
+mod report;
+mod require;
+#[cfg(feature = "std")]
+mod scope;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "symbolize")]
+mod symbolize;
 mod wrapper;
 
 use crate::error::ErrorImpl;
@@ -398,6 +444,606 @@ pub struct Error {
 #[derive(Clone)]
 pub struct Chain<'a> {
     state: crate::chain::ChainState<'a>,
+    layers: Option<crate::ptr::Ref<'a, ErrorImpl>>,
+}
+
+/// A cheaply cloneable, reference counted [`Error`].
+///
+/// `Error` is not `Clone`. When the same failure needs to be delivered to
+/// many recipients, such as every waiter on a shared future, convert it into
+/// a `SharedError` using [`Error::into_shared`] and clone that instead.
+///
+/// `SharedError` implements `std::error::Error` and dereferences to the
+/// original `Error`, so [`downcast_ref`][Error::downcast_ref],
+/// [`chain`][Error::chain], [`root_cause`][Error::root_cause] and the
+/// backtrace all continue to see the original error object.
+///
+/// Converting a `SharedError` back into `anyhow::Error`, for example by `?`,
+/// does not hide the original error behind an opaque layer: the resulting
+/// `Error` has the same chain, and `downcast_ref` reaches through to the
+/// shared error. Because the underlying error is shared, downcasting it by
+/// value or by mutable reference is not possible; those only succeed for
+/// `SharedError` itself.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Result, SharedError};
+/// use std::io;
+///
+/// fn notify(waiter: usize, error: SharedError) -> Result<()> {
+///     # let _ = waiter;
+///     Err(error)?
+/// }
+///
+/// fn main() {
+///     let error = anyhow!(io::Error::new(io::ErrorKind::Other, "oh no!"));
+///     let shared = error.into_shared();
+///
+///     for waiter in 0..3 {
+///         let error = notify(waiter, shared.clone()).unwrap_err();
+///         assert!(error.downcast_ref::<io::Error>().is_some());
+///     }
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone)]
+pub struct SharedError {
+    inner: alloc::sync::Arc<Error>,
+}
+
+/// A collection of independent errors, reported together.
+///
+/// Where [`Error`] models a single failure with a linear chain of causes,
+/// `Errors` holds any number of unrelated failures, such as every problem
+/// found by a validation pass or every job of a batch that failed.
+///
+/// Converting `Errors` into an `anyhow::Error`, for example by `?`, produces
+/// an error whose `{:?}` representation lists every one of the errors as a
+/// numbered tree, and whose [`downcast_ref`][Error::downcast_ref] searches
+/// into each of them.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Errors, Result};
+///
+/// struct Config {
+///     name: String,
+///     port: u16,
+/// }
+///
+/// fn validate(config: &Config) -> Result<()> {
+///     let mut errors = Errors::new();
+///     if config.name.is_empty() {
+///         errors.push(anyhow!("name must not be empty"));
+///     }
+///     if config.port == 0 {
+///         errors.push(anyhow!("port must not be 0"));
+///     }
+///     errors.into_result()
+/// }
+/// ```
+///
+/// An iterator of `Result`s can be collected while keeping every error,
+/// rather than stopping at the first one, using [`Errors::collect`].
+///
+/// ```
+/// use anyhow::{Errors, Result};
+///
+/// fn parse_all(inputs: &[&str]) -> Result<Vec<u16>> {
+///     Errors::collect(inputs.iter().map(|input| input.parse::<u16>()))
+/// }
+/// ```
+pub struct Errors {
+    errors: alloc::vec::Vec<Error>,
+}
+
+/// An error produced from the payload of a panic.
+///
+/// This is the error inside the `anyhow::Error` returned by
+/// [`Error::from_panic`], [`catch_unwind`] and [`join`], and can be obtained
+/// from it with [`downcast_ref`][Error::downcast_ref]. Its Display
+/// representation resembles the message printed by Rust's default panic hook:
+///
+/// ```console
+/// thread 'worker' panicked at src/jobs.rs:47:13: index out of bounds
+/// ```
+///
+/// The location and thread name are only known if the panic happened while
+/// anyhow's panic hook was installed; see [`install_panic_hook`].
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Debug)]
+pub struct Panic {
+    message: Option<alloc::string::String>,
+    thread: Option<alloc::string::String>,
+    location: Option<(alloc::string::String, u32, u32)>,
+}
+
+/// Guard returned by [`scope!`], which leaves the scope when dropped.
+///
+/// Scopes belong to the thread that entered them, so the guard is neither
+/// `Send` nor `Sync`.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[must_use = "the scope is left as soon as the guard is dropped"]
+pub struct ScopeGuard {
+    depth: usize,
+    not_send: core::marker::PhantomData<*const ()>,
+}
+
+/// A value that is shown in error messages, except in the output of
+/// [`Error::redacted`].
+///
+/// Wrap tokens, email addresses, file paths and other values that must not
+/// reach shared logs in `Sensitive` where they go into an error: as the
+/// context, or as an argument of [`anyhow!`], [`bail!`] or [`ensure!`].
+/// Their Display and Debug representations are those of the wrapped value,
+/// except while the error is rendered by [`redacted`][Error::redacted], where
+/// they are `<redacted>`.
+///
+/// ```
+/// use anyhow::{anyhow, Context, Result, Sensitive};
+///
+/// fn authenticate(token: &str) -> Result<()> {
+///     Err(anyhow!("invalid token {}", Sensitive(token)))
+///         .context(Sensitive(format!("request by {}", "alice@example.com")))
+/// }
+///
+/// let error = authenticate("hunter2").unwrap_err();
+/// assert_eq!(
+///     format!("{:#}", error),
+///     "request by alice@example.com: invalid token hunter2",
+/// );
+/// assert_eq!(
+///     format!("{:#}", error.redacted()),
+///     "<redacted>: invalid token <redacted>",
+/// );
+/// ```
+///
+/// Only values that are still wrapped when anyhow renders or formats them
+/// are hidden. A `Sensitive` that the caller has already formatted into a
+/// `String` of its own, for example with `format!`, is in the clear in that
+/// string.
+///
+/// [`anyhow!`]: crate::anyhow
+/// [`bail!`]: crate::bail
+/// [`ensure!`]: crate::ensure
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Sensitive<T>(pub T);
+
+/// Display and Debug adapter for an error with sensitive data hidden.
+///
+/// This type is returned by [`Error::redacted`].
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct Redacted<'a> {
+    error: &'a Error,
+}
+
+/// A pattern of sensitive text that [`Error::redacted`] hides in every
+/// message of an error, registered by [`add_redactor`].
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct Redactor {
+    pattern: redact::Pattern,
+}
+
+/// An error that, unlike [`Error`], may hold values that are not `Send` or
+/// `Sync`.
+///
+/// This is for errors that refer to `Rc` handles, GUI objects and other
+/// things tied to one thread. `LocalError` works like `anyhow::Error`: it
+/// supports [`context`][LocalError::context], [`chain`][LocalError::chain],
+/// downcasting and backtraces, and `?` converts any `std::error::Error` into
+/// it. It is neither `Send` nor `Sync` itself.
+///
+/// A `LocalError` converts back into an `anyhow::Error` with `TryFrom` if
+/// every error in it is `Send` and `Sync`, which is the case when it was
+/// created from an `anyhow::Error` or by
+/// [`new_sendable`][LocalError::new_sendable] and has had no context added to
+/// it since. Any `LocalError` can be turned into an
+/// `anyhow::Error` by [`to_sendable`][LocalError::to_sendable], which keeps
+/// only the messages.
+///
+/// The report handler installed by
+/// [`set_report_handler`][crate::set_report_handler] is not used for a
+/// `LocalError`, whose Debug representation is always the default one.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::LocalError;
+/// use std::fmt::{self, Display};
+/// use std::rc::Rc;
+///
+/// #[derive(Debug)]
+/// struct WidgetError {
+///     widget: Rc<str>,
+/// }
+///
+/// impl Display for WidgetError {
+///     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "widget {} is not visible", self.widget)
+///     }
+/// }
+///
+/// impl std::error::Error for WidgetError {}
+///
+/// fn click(widget: &Rc<str>) -> Result<(), LocalError> {
+///     Err(WidgetError { widget: widget.clone() })?
+/// }
+///
+/// fn run() -> anyhow::Result<()> {
+///     let widget = Rc::from("ok-button");
+///     if let Err(error) = click(&widget) {
+///         let error = error.context("failed to submit form");
+///         assert!(error.downcast_ref::<WidgetError>().is_some());
+///         return Err(error.to_sendable());
+///     }
+///     Ok(())
+/// }
+/// #
+/// # fn main() {
+/// #     let error = run().unwrap_err();
+/// #     assert_eq!(
+/// #         "failed to submit form: widget ok-button is not visible",
+/// #         format!("{:#}", error),
+/// #     );
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub struct LocalError {
+    inner: Error,
+    // Whether every error in the chain is known to be Send and Sync.
+    sendable: bool,
+    not_send: core::marker::PhantomData<*const ()>,
+}
+
+/// An [`Error`] together with a value of a small `Copy` type, usually an
+/// enum, that classifies it.
+///
+/// At an API boundary, callers often need to distinguish a few broad
+/// categories of failure, such as "not found" or "conflict", without caring
+/// about the particular chain of causes. `Kinded<K>` provides [`kind()`] for
+/// that while keeping everything else about the error, and dereferences to
+/// the `anyhow::Error` for the rest.
+///
+/// `Kinded<K>` is the same size as `anyhow::Error`, one pointer. The kind is
+/// kept in the error's heap allocation, so it also survives conversion into a
+/// plain `anyhow::Error` and back. Converting an `anyhow::Error` that has no
+/// kind gives it `K::default()`.
+///
+/// [`kind()`]: Kinded::kind
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{bail, Kinded, KindedContext};
+///
+/// #[derive(Copy, Clone, Debug, Default, PartialEq)]
+/// enum ApiKind {
+///     #[default]
+///     Internal,
+///     NotFound,
+/// }
+///
+/// type Result<T> = std::result::Result<T, Kinded<ApiKind>>;
+///
+/// fn lookup(id: u64) -> Result<String> {
+///     if id == 0 {
+///         bail!(kind: ApiKind::NotFound, "no user with id {}", id);
+///     }
+///     Ok(std::fs::read_to_string(format!("/users/{}", id))?)
+/// }
+///
+/// fn handle(id: u64) -> Result<String> {
+///     lookup(id).context("failed to handle request")
+/// }
+/// #
+/// # fn main() {
+/// #     let error = handle(0).unwrap_err();
+/// #     assert_eq!(error.kind(), ApiKind::NotFound);
+/// #     assert_eq!(format!("{:#}", error), "failed to handle request: no user with id 0");
+/// # }
+/// ```
+pub struct Kinded<K> {
+    error: Error,
+    kind: core::marker::PhantomData<K>,
+}
+
+/// Iterator of the attachments of a given type held by an error.
+///
+/// This type is the iterator returned by [`Error::attachments`].
+///
+/// # Example
+///
+/// ```
+/// use anyhow::Error;
+///
+/// pub struct RequestId(pub u64);
+///
+/// pub fn request_ids(error: &Error) -> Vec<u64> {
+///     error.attachments::<RequestId>().map(|id| id.0).collect()
+/// }
+/// ```
+pub struct Attachments<'a, T> {
+    state: crate::attachment::AttachmentsState<'a>,
+    marker: core::marker::PhantomData<fn() -> &'a T>,
+}
+
+/// A structured, serializable snapshot of an [`Error`].
+///
+/// `Report` captures everything that the human-readable `{:?}`
+/// representation of an error shows, in a form suitable for shipping to a log
+/// pipeline: the outermost message, one entry per layer of the cause chain,
+/// and the resolved frames of the backtrace if one was captured.
+///
+/// `anyhow::Error` itself implements `Serialize` by serializing its
+/// `Report`, so constructing one explicitly is only necessary in order to
+/// inspect or keep the snapshot.
+///
+/// In the other direction, a deserialized `Report` converts into an
+/// `anyhow::Error` whose chain reproduces the Display and Debug
+/// representations of the original one layer at a time. Such an error
+/// reports true from [`Error::is_remote`] and provides the original
+/// backtrace through [`Error::remote_backtrace`]. `anyhow::Error` implements
+/// `Deserialize` in the same way. Deserialization fails on a field that
+/// occurs twice, and on a message that differs from the Display
+/// representation of the outermost layer of the chain.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Report};
+///
+/// let error = anyhow!("oh no!").context("f failed");
+/// let report = Report::new(&error);
+///
+/// assert_eq!("f failed", report.message());
+/// assert_eq!(Some("&str"), report.layers()[1].type_name());
+/// ```
+///
+/// Serialized to JSON, the same report looks like:
+///
+/// ```json
+/// {
+///   "message": "f failed",
+///   "chain": [
+///     {
+///       "display": "f failed",
+///       "debug": "Error { context: \"f failed\", source: oh no! }",
+///       "type_name": "&str"
+///     },
+///     { "display": "oh no!", "debug": "\"oh no!\"", "type_name": "&str" }
+///   ],
+///   "backtrace": []
+/// }
+/// ```
+#[cfg(feature = "serde")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+#[derive(Clone, Debug)]
+pub struct Report {
+    message: alloc::string::String,
+    layers: alloc::vec::Vec<ReportLayer>,
+    frames: alloc::vec::Vec<ReportFrame>,
+}
+
+/// One layer of the cause chain in a [`Report`].
+#[cfg(feature = "serde")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+#[derive(Clone, Debug)]
+pub struct ReportLayer {
+    display: alloc::string::String,
+    debug: alloc::string::String,
+    type_name: Option<alloc::string::String>,
+}
+
+/// One resolved frame of the backtrace in a [`Report`].
+#[cfg(feature = "serde")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+#[derive(Clone, Debug)]
+pub struct ReportFrame {
+    function: Option<alloc::string::String>,
+    file: Option<alloc::string::String>,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+/// A backtrace that was captured by a different process, and received as part
+/// of a serialized [`Report`].
+///
+/// This type is returned by [`Error::remote_backtrace`]. Its Display
+/// representation is formatted the same way as a locally captured backtrace.
+#[cfg(feature = "serde")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "serde")))]
+#[derive(Clone, Debug)]
+pub struct RemoteBacktrace {
+    frames: alloc::vec::Vec<ReportFrame>,
+}
+
+/// One symbol of a captured backtrace, as returned by
+/// [`Error::backtrace_frames`].
+///
+/// A single frame of the call stack resolves to several symbols when
+/// functions were inlined into it. Each of those is reported separately, with
+/// every symbol except the outermost one marked as [inlined].
+///
+/// [inlined]: BacktraceFrame::is_inlined
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug)]
+pub struct BacktraceFrame {
+    ip: Option<usize>,
+    name: Option<alloc::string::String>,
+    file: Option<std::path::PathBuf>,
+    line: Option<u32>,
+    column: Option<u32>,
+    inlined: bool,
+}
+
+/// Iterator of the symbols of a captured backtrace, innermost call first.
+///
+/// This type is returned by [`Error::backtrace_frames`].
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Clone, Debug)]
+pub struct BacktraceFrames {
+    frames: alloc::vec::IntoIter<BacktraceFrame>,
+}
+
+/// A backtrace that has been captured but not symbolized: the raw instruction
+/// pointers of its frames, and the modules of the process they belong to.
+///
+/// This is returned by [`Error::raw_backtrace`]. Its Display representation
+/// is a small text dump that can be stored or sent somewhere else, parsed
+/// back with `str::parse`, and [resolved] against unstripped copies of the
+/// binaries, or their separate debug files. The `anyhow-symbolize` binary
+/// does this from the command line.
+///
+/// ```
+/// # fn main() -> anyhow::Result<()> {
+/// use anyhow::{anyhow, RawBacktrace};
+///
+/// let error = anyhow!("oh no!");
+/// if let Some(raw) = error.raw_backtrace() {
+///     let dump = raw.to_string();
+///
+///     // ... later, possibly on a different machine ...
+///     let raw: RawBacktrace = dump.parse()?;
+///     for frame in raw.resolve(&[std::env::current_exe()?])? {
+///         println!("{:?}", frame.name());
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [resolved]: RawBacktrace::resolve
+#[cfg(feature = "symbolize")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "symbolize")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawBacktrace {
+    frames: alloc::vec::Vec<usize>,
+    modules: alloc::vec::Vec<RawModule>,
+}
+
+/// An executable or shared library that was loaded in the process that
+/// captured a [`RawBacktrace`].
+#[cfg(feature = "symbolize")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "symbolize")))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RawModule {
+    path: std::path::PathBuf,
+    start: usize,
+    end: usize,
+    base: usize,
+    build_id: Option<alloc::vec::Vec<u8>>,
+}
+
+/// The error produced by [`ensure!`] when it is not given an error message of
+/// its own.
+///
+/// It describes the condition that failed, and for comparisons such as
+/// `ensure!(a == b)`, the values that were compared. The Display
+/// representation is a one-line summary, like ``Condition failed: `a == b` (1
+/// vs 2)``. The error can also be downcast to a `String` holding that
+/// summary.
+///
+/// ```
+/// use anyhow::{ensure, ConditionFailed, Result};
+///
+/// fn check(len: usize) -> Result<()> {
+///     ensure!(len < 16);
+///     Ok(())
+/// }
+///
+/// let error = check(20).unwrap_err();
+/// let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+/// assert_eq!(condition.expression(), "len < 16");
+/// assert_eq!(condition.operator(), Some("<"));
+/// assert_eq!(condition.lhs(), Some("20"));
+/// assert_eq!(condition.rhs(), Some("16"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConditionFailed {
+    expression: &'static str,
+    operator: Option<&'static str>,
+    lhs: Option<alloc::string::String>,
+    rhs: Option<alloc::string::String>,
+    pretty: Option<(alloc::string::String, alloc::string::String)>,
+    verbose: bool,
+    clauses: alloc::vec::Vec<ConditionFailed>,
+    file: &'static str,
+    line: u32,
+    // The Display representation, rendered once the condition has failed.
+    // The error also exposes it as a String, which is what ensure! produced
+    // before ConditionFailed existed.
+    message: alloc::string::String,
+}
+
+/// The message of an error created by [`anyhow!`], [`bail!`] or [`ensure!`]
+/// from a format string and arguments.
+///
+/// Besides the interpolated message, which is what the error displays, this
+/// keeps the format string and the values of the arguments, for log
+/// aggregation that groups messages by template, or for translating messages.
+/// The values are recorded by their Debug representation, or by their
+/// Display representation if they do not implement Debug, and are truncated
+/// after 256 bytes.
+///
+/// An error holding a `FormattedMessage` can also be downcast to the `String`
+/// of its interpolated message.
+///
+/// ```
+/// use anyhow::{anyhow, FormattedMessage};
+///
+/// let path = "/etc/app.toml";
+/// let error = anyhow!("failed to open {} (errno {errno})", path, errno = 2);
+/// assert_eq!(error.to_string(), "failed to open /etc/app.toml (errno 2)");
+///
+/// let message = error.downcast_ref::<FormattedMessage>().unwrap();
+/// assert_eq!(message.template(), "failed to open {} (errno {errno})");
+/// assert_eq!(message.args(), ["\"/etc/app.toml\""]);
+/// assert_eq!(message.named_arg("errno"), Some("2"));
+///
+/// let string = error.downcast_ref::<String>().unwrap();
+/// assert_eq!(string, "failed to open /etc/app.toml (errno 2)");
+/// ```
+///
+/// Arguments that the format string captures implicitly, as in
+/// `anyhow!("failed to open {path}")`, are not visible to the macro. They are
+/// recorded among the named arguments by the text that they produced in the
+/// message, as long as the text around their placeholder in the format string
+/// tells where that text starts and ends. Pass them explicitly as
+/// `path = path` to have their Debug representation instead.
+///
+/// ```
+/// use anyhow::{anyhow, FormattedMessage};
+///
+/// let path = "/etc/app.toml";
+/// let error = anyhow!("failed to open {path}");
+///
+/// let message = error.downcast_ref::<FormattedMessage>().unwrap();
+/// assert_eq!(message.named_arg("path"), Some("/etc/app.toml"));
+/// ```
+///
+/// [`anyhow!`]: crate::anyhow
+/// [`bail!`]: crate::bail
+/// [`ensure!`]: crate::ensure
+#[derive(Clone)]
+pub struct FormattedMessage {
+    message: alloc::string::String,
+    template: &'static str,
+    args: alloc::vec::Vec<alloc::string::String>,
+    named_args: alloc::vec::Vec<(&'static str, alloc::string::String)>,
+    #[cfg(feature = "std")]
+    sensitive: alloc::vec::Vec<(usize, usize)>,
 }
 
 /// `Result<T, Error>`
@@ -611,6 +1257,426 @@ pub trait Context<T, E>: context::private::Sealed {
         F: FnOnce() -> C;
 }
 
+/// Provides the `context` method for a `Future` whose output is a `Result`.
+///
+/// This is the asynchronous counterpart of [`Context`]. The context is
+/// attached when the future resolves to an error, and the [location] recorded
+/// for it is the place where `.context()` was called rather than somewhere
+/// inside the executor.
+///
+/// This trait is sealed and cannot be implemented for types outside of
+/// `anyhow`.
+///
+/// [location]: Error::location
+///
+/// # Example
+///
+/// ```
+/// # mod net {
+/// #     pub async fn fetch(_: &str) -> std::io::Result<Vec<u8>> {
+/// #         unimplemented!()
+/// #     }
+/// # }
+/// #
+/// use anyhow::{FutureContext, Result};
+///
+/// async fn download(url: &str) -> Result<Vec<u8>> {
+///     net::fetch(url)
+///         .with_context(|| format!("failed to download {}", url))
+///         .await
+/// }
+/// ```
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+pub trait FutureContext<T, E>: future::private::SealedFuture + Sized {
+    /// Wrap the error value of the future with additional context.
+    fn context<C>(self, context: C) -> ContextFuture<Self, C>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value of the future with additional context that is
+    /// evaluated lazily only once an error does occur.
+    fn with_context<C, F>(self, f: F) -> WithContextFuture<Self, F>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+/// Provides the `context` method for a `Stream` whose items are `Result`s.
+///
+/// Every error produced by the stream is wrapped with the context. Because
+/// there can be more than one, `context` requires the context to be `Clone`,
+/// and the closure given to `with_context` is called once per error.
+///
+/// This trait is sealed and cannot be implemented for types outside of
+/// `anyhow`.
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+pub trait StreamContext<T, E>: future::private::SealedStream + Sized {
+    /// Wrap each error value of the stream with additional context.
+    fn context<C>(self, context: C) -> ContextStream<Self, C>
+    where
+        C: Display + Clone + Send + Sync + 'static;
+
+    /// Wrap each error value of the stream with additional context that is
+    /// evaluated lazily only once an error does occur.
+    fn with_context<C, F>(self, f: F) -> WithContextStream<Self, F>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnMut() -> C;
+}
+
+/// Future returned by [`FutureContext::context`].
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ContextFuture<Fut, C> {
+    future: Fut,
+    context: Option<C>,
+    location: Option<&'static core::panic::Location<'static>>,
+}
+
+/// Future returned by [`FutureContext::with_context`].
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct WithContextFuture<Fut, F> {
+    future: Fut,
+    f: Option<F>,
+    location: Option<&'static core::panic::Location<'static>>,
+}
+
+/// Stream returned by [`StreamContext::context`].
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+#[must_use = "streams do nothing unless polled"]
+pub struct ContextStream<S, C> {
+    stream: S,
+    context: C,
+    location: Option<&'static core::panic::Location<'static>>,
+}
+
+/// Stream returned by [`StreamContext::with_context`].
+#[cfg(feature = "futures")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "futures")))]
+#[must_use = "streams do nothing unless polled"]
+pub struct WithContextStream<S, F> {
+    stream: S,
+    f: F,
+    location: Option<&'static core::panic::Location<'static>>,
+}
+
+/// Provides the `context` method for a `Result` whose error is a
+/// [`Kinded`], keeping the kind.
+///
+/// This trait is sealed and cannot be implemented for types outside of
+/// `anyhow`.
+pub trait KindedContext<T, K>: kinded::private::Sealed {
+    /// Wrap the error value with additional context.
+    fn context<C>(self, context: C) -> Result<T, Kinded<K>>
+    where
+        C: Display + Send + Sync + 'static;
+
+    /// Wrap the error value with additional context that is evaluated lazily
+    /// only once an error does occur.
+    fn with_context<C, F>(self, f: F) -> Result<T, Kinded<K>>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C;
+}
+
+/// Renders the `{:?}` representation of errors in place of anyhow's default
+/// format.
+///
+/// A report handler is created for each error at the moment the error is
+/// constructed, by the function installed with [`set_report_handler`]. This
+/// makes it possible for the handler to capture state belonging to that
+/// particular error, such as a timestamp or the current span of a tracing
+/// library, and include it when the error is eventually printed. The handler
+/// of an error can be retrieved with [`Error::report_handler`].
+///
+/// Adding context to an error does not create a new handler; the error keeps
+/// the one that was created with it.
+pub trait ReportHandler: core::any::Any + Send + Sync {
+    /// Render the Debug representation of `error`.
+    ///
+    /// This is used for both `{:?}` and `{:#?}`. Use `f.alternate()` to tell
+    /// them apart.
+    fn debug(&self, error: &Error, f: &mut core::fmt::Formatter) -> core::fmt::Result;
+}
+
+/// The report handler that produces anyhow's built-in `{:?}` representation.
+///
+/// Errors that were created while no report handler was installed are
+/// rendered by this handler. Custom handlers can delegate to it for the parts
+/// of the format they do not want to change.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultHandler;
+
+/// Error returned by [`set_report_handler`] if a report handler has already
+/// been installed.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Debug)]
+pub struct InstallError;
+
+/// Install the function that creates the [`ReportHandler`] for each new
+/// error.
+///
+/// This can be done only once per process. Errors created before the handler
+/// is installed continue to be rendered by the [`DefaultHandler`].
+///
+/// The function is called during the construction of every `anyhow::Error`,
+/// with the newly created error object, and must not itself create any
+/// `anyhow::Error`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Error, ReportHandler};
+/// use std::fmt;
+///
+/// struct OneLine;
+///
+/// impl ReportHandler for OneLine {
+///     fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+///         write!(f, "{:#}", error)
+///     }
+/// }
+///
+/// fn main() {
+///     anyhow::set_report_handler(|_| Box::new(OneLine)).unwrap();
+///
+///     let error = anyhow!("oh no!").context("f failed");
+///     assert_eq!("f failed: oh no!", format!("{:?}", error));
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_report_handler<F>(hook: F) -> Result<(), InstallError>
+where
+    F: Fn(&(dyn StdError + 'static)) -> Box<dyn ReportHandler> + Send + Sync + 'static,
+{
+    handler::install(Box::new(hook))
+}
+
+/// Hide frames matching a predicate from the backtraces in the `{:?}`
+/// representation of errors.
+///
+/// The predicate is given the demangled name of the function of each frame,
+/// and its source file as it appears in the rendered backtrace, and returns
+/// true for frames that should be hidden. Any number of predicates can be
+/// registered; a frame is hidden if any one of them matches it.
+///
+/// Independently of any registered predicate, anyhow always hides the frames
+/// of its own error construction path at the top of a backtrace, and the
+/// frames of the runtime below `main` or below the entry point of a thread.
+/// The number of frames that were hidden is shown at the end of the
+/// backtrace.
+///
+/// # Example
+///
+/// ```
+/// fn main() {
+///     anyhow::add_frame_filter(|function, _file| {
+///         function.starts_with("tokio::") || function.starts_with("<tokio::")
+///     });
+///
+///     anyhow::add_frame_filter(|_function, file| {
+///         file.map_or(false, |file| file.contains("/.cargo/registry/"))
+///     });
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn add_frame_filter<F>(filter: F)
+where
+    F: Fn(&str, Option<&str>) -> bool + Send + Sync + 'static,
+{
+    crate::backtrace::filter::add(Box::new(filter));
+}
+
+/// Register a pattern of sensitive text to hide when an error is rendered by
+/// [`Error::redacted`].
+///
+/// Redactors are applied to the text of each message of the error: the
+/// error's own message, its causes, its scopes and its printable
+/// attachments. This catches sensitive data that did not go into the error
+/// as [`Sensitive`], such as that in the message of an I/O error. Every part
+/// of a message that some registered redactor matches is replaced by
+/// `<redacted>`.
+///
+/// # Example
+///
+/// ```
+/// use anyhow::{anyhow, Redactor};
+///
+/// fn main() {
+///     anyhow::add_redactor(Redactor::after("Bearer "));
+///     anyhow::add_redactor(Redactor::word(|word| word.contains('@')));
+///
+///     let error = anyhow!("alice@example.com sent Bearer abc123: forbidden");
+///     assert_eq!(
+///         error.redacted().to_string(),
+///         "<redacted> sent Bearer <redacted>: forbidden",
+///     );
+/// }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn add_redactor(redactor: Redactor) {
+    crate::redact::add(redactor);
+}
+
+/// When to capture a backtrace for a newly created error.
+///
+/// The policy is set for the whole process by [`set_backtrace_policy`], and
+/// can be overridden for part of a single thread by
+/// [`with_backtrace_policy`].
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BacktracePolicy {
+    /// Capture if the `RUST_LIB_BACKTRACE` or `RUST_BACKTRACE` environment
+    /// variable asks for it, as described for [`Error::backtrace`]. The
+    /// variables are read only once per process. This is the default.
+    Env,
+    /// Capture a backtrace for every error.
+    Always,
+    /// Never capture a backtrace.
+    Never,
+    /// Capture a backtrace for 1 in every `n` errors, regardless of the
+    /// environment. `Sampled(0)` never captures.
+    Sampled(u32),
+}
+
+/// Set the [`BacktracePolicy`] for errors created from now on, on any thread.
+///
+/// Unlike the environment variables, which are read once, this can be
+/// changed at any time, for example to turn backtraces on in a long-running
+/// process while investigating a problem.
+///
+/// ```
+/// use anyhow::BacktracePolicy;
+///
+/// // Backtraces are expensive; keep one in a hundred.
+/// anyhow::set_backtrace_policy(BacktracePolicy::Sampled(100));
+/// ```
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn set_backtrace_policy(policy: BacktracePolicy) {
+    crate::backtrace::policy::set(policy);
+}
+
+/// Run `f` with a different [`BacktracePolicy`] for errors created on the
+/// current thread.
+///
+/// This takes precedence over the policy set by [`set_backtrace_policy`].
+/// The previous policy of the thread is restored when `f` returns or panics.
+///
+/// ```
+/// use anyhow::{anyhow, BacktracePolicy};
+///
+/// let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || anyhow!("oh no!"));
+/// assert_eq!(error.backtrace_frames().len(), 0);
+/// ```
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn with_backtrace_policy<F, R>(policy: BacktracePolicy, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    crate::backtrace::policy::scoped(policy, f)
+}
+
+/// Set the length in bytes after which the values shown by
+/// `ensure!(verbose: ...)` are truncated.
+///
+/// The default is 1024. Truncated values end with `…`. The limit applies to
+/// conditions that fail after it is set; an existing error keeps the values
+/// it was created with.
+///
+/// ```
+/// // Keep error messages short enough for a single log line.
+/// anyhow::set_ensure_operand_limit(200);
+/// ```
+pub fn set_ensure_operand_limit(limit: usize) {
+    crate::ensure::set_operand_limit(limit);
+}
+
+/// Invoke a closure, converting a panic inside of it into an error.
+///
+/// This is [`std::panic::catch_unwind`] with the payload turned into an
+/// error by [`Error::from_panic`]. The location and backtrace of the panic
+/// are only known if anyhow's panic hook is installed, which this function
+/// does not do on its own; call [`install_panic_hook`] once at startup.
+///
+/// ```
+/// use anyhow::Panic;
+///
+/// anyhow::install_panic_hook();
+///
+/// let error = anyhow::catch_unwind(|| {
+///     panic!("oh no!");
+/// })
+/// .unwrap_err();
+///
+/// let panic = error.downcast_ref::<Panic>().unwrap();
+/// assert_eq!(panic.message(), Some("oh no!"));
+/// assert_eq!(panic.line(), Some(line!() - 6));
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn catch_unwind<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> R + std::panic::UnwindSafe,
+{
+    crate::panic::catch_unwind(f)
+}
+
+/// Wait for a thread to finish, converting a panic of that thread into an
+/// error.
+///
+/// The location and backtrace of the panic are only known if anyhow's panic
+/// hook had been installed by the time the thread panicked, by
+/// [`install_panic_hook`].
+///
+/// ```
+/// use std::thread;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// anyhow::install_panic_hook();
+///
+/// let handle = thread::Builder::new()
+///     .name("worker".to_owned())
+///     .spawn(|| 1 + 1)?;
+/// let sum = anyhow::join(handle)?;
+/// # assert_eq!(sum, 2);
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub fn join<T>(handle: std::thread::JoinHandle<T>) -> Result<T> {
+    crate::panic::join(handle)
+}
+
+/// Install a panic hook that records the location, thread name and backtrace
+/// of each panic, for [`Error::from_panic`] to use.
+///
+/// This replaces the process-wide panic hook by way of
+/// [`std::panic::set_hook`], so it is up to the application to call it; no
+/// other function of anyhow installs it. The hook then calls whichever hook
+/// was installed before it, so panic messages are still printed as usual.
+/// Installing it more than once has no effect.
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+pub fn install_panic_hook() {
+    crate::panic::install_hook();
+}
+
 /// Equivalent to Ok::<_, anyhow::Error>(value).
 ///
 /// This simplifies creation of an anyhow::Result in places where type inference
@@ -637,14 +1703,21 @@ pub fn Ok<T>(t: T) -> Result<T> {
 // Not public API. Referenced by macro-generated code.
 #[doc(hidden)]
 pub mod __private {
-    use crate::Error;
+    use crate::{Error, FormattedMessage};
+    #[cfg(not(feature = "std"))]
     use alloc::fmt;
+    use alloc::string::String;
+    use alloc::vec::Vec;
     use core::fmt::Arguments;
 
-    pub use crate::ensure::{BothDebug, NotBothDebug};
+    pub use crate::ensure::{condition_failed, BothDebug, Clauses, IsDebug, NotBothDebug, NotDebug};
+    pub use crate::require::{Missing, Require};
+    #[cfg(feature = "std")]
+    pub use crate::scope::enter as enter_scope;
     pub use alloc::format;
-    pub use core::result::Result::Err;
-    pub use core::{concat, format_args, stringify};
+    pub use alloc::vec;
+    pub use core::result::Result::{Err, Ok};
+    pub use core::{concat, file, format_args, line, stringify};
 
     #[doc(hidden)]
     pub mod kind {
@@ -654,24 +1727,61 @@ pub mod __private {
         pub use crate::kind::BoxedKind;
     }
 
+    #[doc(hidden)]
+    pub mod args {
+        pub use crate::formatted::{DebugArg, DisplayArg, OpaqueArg};
+    }
+
     #[doc(hidden)]
     #[inline]
     #[cold]
-    pub fn format_err(args: Arguments) -> Error {
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn format_err(template: &'static str, args: Arguments) -> Error {
         #[cfg(anyhow_no_fmt_arguments_as_str)]
         let fmt_arguments_as_str = None::<&str>;
         #[cfg(not(anyhow_no_fmt_arguments_as_str))]
+        #[allow(clippy::incompatible_msrv)]
         let fmt_arguments_as_str = args.as_str();
 
         if let Some(message) = fmt_arguments_as_str {
             // anyhow!("literal"), can downcast to &'static str
             Error::msg(message)
         } else {
-            // anyhow!("interpolate {var}"), can downcast to String
-            Error::msg(fmt::format(args))
+            // anyhow!("interpolate {var}"), can downcast to String and to
+            // FormattedMessage
+            format_err_interpolated(template, args, Vec::new(), Vec::new())
         }
     }
 
+    #[doc(hidden)]
+    #[inline]
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn format_err_interpolated(
+        template: &'static str,
+        args: Arguments,
+        positional: Vec<String>,
+        mut named: Vec<(&'static str, String)>,
+    ) -> Error {
+        // anyhow!("interpolate {}", var), can downcast to String and to
+        // FormattedMessage
+        #[cfg(feature = "std")]
+        let (message, sensitive) = crate::redact::format(args);
+        #[cfg(not(feature = "std"))]
+        let (message, sensitive) = (fmt::format(args), Vec::new());
+        let implicit = crate::formatted::implicit_args(template, &message, &named, &sensitive);
+        named.extend(implicit);
+        let message = FormattedMessage {
+            message,
+            template,
+            args: positional,
+            named_args: named,
+            #[cfg(feature = "std")]
+            sensitive,
+        };
+        Error::from_formatted(message, backtrace!())
+    }
+
     #[doc(hidden)]
     #[inline]
     #[cold]
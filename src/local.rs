@@ -0,0 +1,203 @@
+use crate::chain::Chain;
+use crate::error::ErrorImpl;
+use crate::{Error, LocalError, StdError};
+use core::convert::TryFrom;
+use core::fmt::{self, Debug, Display};
+use core::marker::PhantomData;
+use core::panic::Location;
+
+impl LocalError {
+    /// Create a new error object from any error type, including ones that are
+    /// not `Send` or `Sync`.
+    ///
+    /// If the error type does not provide a backtrace, a backtrace will be
+    /// created here to ensure that a backtrace exists.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn new<E>(error: E) -> Self
+    where
+        E: StdError + 'static,
+    {
+        let backtrace = backtrace_if_absent!(&error);
+        LocalError {
+            // Safety: the Error never leaves this LocalError.
+            inner: unsafe { Error::from_local(error, backtrace) },
+            sendable: false,
+            not_send: PhantomData,
+        }
+    }
+
+    /// Create a new error object from an error type that is `Send` and
+    /// `Sync`.
+    ///
+    /// Unlike one created by [`new`][LocalError::new], the resulting
+    /// `LocalError` can be converted back into an [`Error`] with `TryFrom`.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn new_sendable<E>(error: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        LocalError::from(Error::new(error))
+    }
+
+    /// Wrap the error value with additional context, which need not be `Send`
+    /// or `Sync`.
+    ///
+    /// This is otherwise the same as [`Error::context`]. Since the context
+    /// may not be `Send` or `Sync`, the result can no longer be converted
+    /// into an [`Error`]; add context to the `Error` before converting it
+    /// into a `LocalError` where that is needed.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + 'static,
+    {
+        LocalError {
+            // Safety: the Error never leaves this LocalError.
+            inner: unsafe { self.inner.local_context(context) },
+            sendable: false,
+            not_send: PhantomData,
+        }
+    }
+
+    /// An iterator of the chain of source errors contained by this error.
+    ///
+    /// This is the same as [`Error::chain`].
+    #[cold]
+    pub fn chain(&self) -> Chain<'_> {
+        self.inner.chain()
+    }
+
+    /// The lowest level cause of this error &mdash; this error's cause's
+    /// cause's cause etc.
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.inner.root_cause()
+    }
+
+    /// Returns true if `E` is the type held by this error object.
+    pub fn is<E>(&self) -> bool
+    where
+        E: Display + Debug + 'static,
+    {
+        self.downcast_ref::<E>().is_some()
+    }
+
+    /// Attempt to downcast the error object to a concrete type.
+    pub fn downcast<E>(self) -> Result<E, Self>
+    where
+        E: Display + Debug + 'static,
+    {
+        let sendable = self.sendable;
+        match self.inner.downcast_any() {
+            Ok(error) => Ok(error),
+            Err(inner) => Err(LocalError {
+                inner,
+                sendable,
+                not_send: PhantomData,
+            }),
+        }
+    }
+
+    /// Downcast this error object by reference.
+    pub fn downcast_ref<E>(&self) -> Option<&E>
+    where
+        E: Display + Debug + 'static,
+    {
+        self.inner.downcast_ref_any()
+    }
+
+    /// Downcast this error object by mutable reference.
+    pub fn downcast_mut<E>(&mut self) -> Option<&mut E>
+    where
+        E: Display + Debug + 'static,
+    {
+        self.inner.downcast_mut_any()
+    }
+
+    /// Get the backtrace for this error.
+    ///
+    /// This is the same as [`Error::backtrace`].
+    #[cfg(any(std_backtrace, feature = "backtrace"))]
+    pub fn backtrace(&self) -> &impl_backtrace!() {
+        self.inner.backtrace()
+    }
+
+    /// The source location at which this error was created, or at which the
+    /// outermost context was attached to it.
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.inner.location()
+    }
+
+    /// Convert into an `anyhow::Error` that keeps only the Display
+    /// representation of each error in the chain.
+    ///
+    /// Downcasting the result finds only `String`s, and its backtrace is
+    /// captured anew.
+    #[cold]
+    #[must_use]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    pub fn to_sendable(&self) -> Error {
+        let mut chain = self.chain().rev();
+        let root = chain.next().unwrap().to_string();
+        let mut error = Error::msg(root);
+        for cause in chain {
+            error = error.context(cause.to_string());
+        }
+        error
+    }
+}
+
+impl<E> From<E> for LocalError
+where
+    E: StdError + 'static,
+{
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(error: E) -> Self {
+        LocalError::new(error)
+    }
+}
+
+impl From<Error> for LocalError {
+    fn from(error: Error) -> Self {
+        LocalError {
+            inner: error,
+            sendable: true,
+            not_send: PhantomData,
+        }
+    }
+}
+
+/// Succeeds if every error in the chain is `Send` and `Sync`, which is the
+/// case if the `LocalError` was created from an `anyhow::Error` or by
+/// [`LocalError::new_sendable`], and no context was added to it since.
+impl TryFrom<LocalError> for Error {
+    type Error = LocalError;
+
+    fn try_from(error: LocalError) -> Result<Self, LocalError> {
+        if error.sendable {
+            Ok(error.inner)
+        } else {
+            Err(error)
+        }
+    }
+}
+
+// Not through the installed report handler, which would be given the inner
+// Error as an &Error even though it may not be Send or Sync.
+impl Debug for LocalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        unsafe { ErrorImpl::debug(self.inner.inner.by_ref(), f) }
+    }
+}
+
+impl Display for LocalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
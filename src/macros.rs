@@ -6,6 +6,10 @@
 /// The surrounding function's or closure's return value is required to be
 /// `Result<_,`[`anyhow::Error`][crate::Error]`>`.
 ///
+/// Prefixing the arguments with `kind: K,` returns a
+/// [`Kinded<K>`][crate::Kinded] with the given kind instead, as in
+/// `bail!(kind: ApiKind::NotFound, "no user {}", id)`.
+///
 /// [anyhow!]: crate::anyhow
 ///
 /// # Example
@@ -55,6 +59,9 @@
 /// ```
 #[macro_export]
 macro_rules! bail {
+    (kind: $kind:expr, $($msg:tt)+) => {
+        return $crate::__private::Err($crate::Kinded::new($kind, $crate::__anyhow!($($msg)+)))
+    };
     ($msg:literal $(,)?) => {
         return $crate::__private::Err($crate::__anyhow!($msg))
     };
@@ -78,6 +85,37 @@ macro_rules! bail {
 /// if the condition fails. Unlike `assert!`, `ensure!` returns an `Error`
 /// rather than panicking.
 ///
+/// If no error message is given, the error describes the failed condition
+/// and can be downcast to [`ConditionFailed`][crate::ConditionFailed].
+///
+/// A condition made of clauses joined by `&&` or `||` is evaluated clause by
+/// clause, with the usual short-circuiting, and the error lists the clauses
+/// that were false along with their operands. A `matches!(value, pattern)`
+/// clause reports the value that did not match, and `!(a == b)` reports both
+/// sides of the comparison.
+///
+/// By default the values of a failed comparison are only included in the
+/// message if they are short. Prefixing the condition with `verbose:` shows
+/// both values in full on separate lines, truncated at the limit set by
+/// [`set_ensure_operand_limit`][crate::set_ensure_operand_limit], and shows a
+/// line diff of their `{:#?}` representations if both span multiple lines.
+///
+/// ```
+/// # use anyhow::{ensure, Result};
+/// #
+/// # fn main() -> Result<()> {
+/// #     let expected = vec!["a"];
+/// #     let actual = vec!["a"];
+/// #
+/// ensure!(verbose: actual == expected);
+/// #     Ok(())
+/// # }
+/// ```
+///
+/// Prefixing the arguments with `kind: K,` returns a
+/// [`Kinded<K>`][crate::Kinded] with the given kind instead of an `Error`,
+/// as in `ensure!(kind: ApiKind::Conflict, !exists, "already exists")`.
+///
 /// [anyhow!]: crate::anyhow
 ///
 /// # Example
@@ -119,10 +157,26 @@ macro_rules! bail {
 #[cfg(doc)]
 #[macro_export]
 macro_rules! ensure {
+    (kind: $kind:expr, $($tt:tt)*) => {
+        $crate::__parse_ensure!(
+            /* state */ 0
+            /* stack */ ()
+            /* bail */ (kind: $kind, $($tt)*)
+            /* fuel */ (~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~)
+            /* parse */ {()}
+            /* dup */ ($($tt)*)
+            /* rest */ $($tt)*
+        )
+    };
+    (verbose: $($tt:tt)*) => {
+        $crate::ensure!($($tt)*)
+    };
     ($cond:expr $(,)?) => {
         if !$cond {
-            return $crate::__private::Err($crate::Error::msg(
-                $crate::__private::concat!("Condition failed: `", $crate::__private::stringify!($cond), "`")
+            return $crate::__private::Err($crate::__private::condition_failed(
+                $crate::__private::stringify!($cond),
+                $crate::__private::file!(),
+                $crate::__private::line!(),
             ));
         }
     };
@@ -146,6 +200,28 @@ macro_rules! ensure {
 #[cfg(not(doc))]
 #[macro_export]
 macro_rules! ensure {
+    (kind: $kind:expr, $($tt:tt)*) => {
+        $crate::__parse_ensure!(
+            /* state */ 0
+            /* stack */ ()
+            /* bail */ (kind: $kind, $($tt)*)
+            /* fuel */ (~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~)
+            /* parse */ {()}
+            /* dup */ ($($tt)*)
+            /* rest */ $($tt)*
+        )
+    };
+    (verbose: $($tt:tt)*) => {
+        $crate::__parse_ensure!(
+            /* state */ 0
+            /* stack */ ()
+            /* bail */ (verbose: $($tt)*)
+            /* fuel */ (~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~ ~~~~~~~~~~)
+            /* parse */ {()}
+            /* dup */ ($($tt)*)
+            /* rest */ $($tt)*
+        )
+    };
     ($($tt:tt)*) => {
         $crate::__parse_ensure!(
             /* state */ 0
@@ -159,6 +235,183 @@ macro_rules! ensure {
     };
 }
 
+/// Unwrap an `Option` or `Result`, or return early with an error.
+///
+/// This macro evaluates to the value inside of `Some` or `Ok`. Otherwise it
+/// returns from the surrounding function like [`bail!`], which requires the
+/// function's or closure's return value to be
+/// `Result<_,`[`anyhow::Error`][crate::Error]`>`.
+///
+/// The message forms are the same as for [`anyhow!`][crate::anyhow]. For an
+/// `Err` the original error is kept as the source of the message, as if
+/// attached with [`Context`][crate::Context]. Without a message, an `Err` is
+/// returned as is and `None` becomes an error naming the expression.
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{require, Result};
+/// # use std::collections::HashMap;
+/// #
+/// fn port(config: &HashMap<&str, String>) -> Result<u16> {
+///     let port = require!(config.get("port"), "missing key `port`");
+///     let port = require!(port.parse(), "invalid port {:?}", port);
+///     Ok(port)
+/// }
+/// #
+/// # fn main() {
+/// #     let mut config = HashMap::new();
+/// #     assert!(port(&config).is_err());
+/// #     config.insert("port", "x".to_owned());
+/// #     assert!(port(&config).unwrap_err().source().is_some());
+/// #     config.insert("port", "80".to_owned());
+/// #     assert_eq!(port(&config).unwrap(), 80);
+/// # }
+/// ```
+#[macro_export]
+macro_rules! require {
+    ($value:expr $(,)?) => {
+        match $crate::__private::Require::__require($value) {
+            $crate::__private::Ok(value) => value,
+            $crate::__private::Err(missing) => {
+                return $crate::__private::Err($crate::__private::Missing::__into_error(
+                    missing,
+                    $crate::__private::stringify!($value),
+                ));
+            }
+        }
+    };
+    ($value:expr, $($message:tt)+) => {
+        match $crate::__private::Require::__require($value) {
+            $crate::__private::Ok(value) => value,
+            $crate::__private::Err(missing) => {
+                return $crate::__private::Err($crate::__private::Missing::__with_message(
+                    missing,
+                    $crate::__anyhow!($($message)+),
+                ));
+            }
+        }
+    };
+}
+
+/// Destructure a value with a refutable pattern, or return early with an
+/// error.
+///
+/// `ensure_let!(Some(x) = value)` binds `x` for the rest of the enclosing
+/// block, like `let Some(x) = value else { bail!(...) };`. It expands to a
+/// `let`-`else` statement and so is only available when anyhow is compiled
+/// with Rust 1.65 or newer.
+///
+/// The message forms are the same as for [`anyhow!`][crate::anyhow]. Without
+/// a message, the error describes the pattern and can be downcast to
+/// [`ConditionFailed`][crate::ConditionFailed].
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{ensure_let, Result};
+/// #
+/// enum Shape {
+///     Circle { radius: f64 },
+///     Square { side: f64 },
+/// }
+///
+/// fn radius(shape: Shape) -> Result<f64> {
+///     ensure_let!(Shape::Circle { radius } = shape, "not a circle");
+///     Ok(radius)
+/// }
+/// #
+/// # fn main() {
+/// #     assert_eq!(radius(Shape::Circle { radius: 1.0 }).unwrap(), 1.0);
+/// #     assert!(radius(Shape::Square { side: 1.0 }).is_err());
+/// # }
+/// ```
+#[cfg(not(anyhow_no_let_else))]
+#[macro_export]
+macro_rules! ensure_let {
+    ($($pat:pat)|+ = $value:expr $(,)?) => {
+        let ($($pat)|+) = ($value) else {
+            return $crate::__private::Err($crate::__private::condition_failed(
+                $crate::__private::concat!(
+                    "let ",
+                    $crate::__private::stringify!($($pat)|+),
+                    " = ",
+                    $crate::__private::stringify!($value),
+                ),
+                $crate::__private::file!(),
+                $crate::__private::line!(),
+            ));
+        };
+    };
+    ($($pat:pat)|+ = $value:expr, $($message:tt)+) => {
+        let ($($pat)|+) = ($value) else {
+            return $crate::__private::Err($crate::__anyhow!($($message)+));
+        };
+    };
+}
+
+/// Describe what the current thread is in the middle of, for every error
+/// created until the returned guard is dropped.
+///
+/// This takes a format string with arguments, like [`format!`]. Each error
+/// constructed on this thread while the [guard] is alive records the scopes
+/// that were active at that moment, and its `{:?}` representation lists them
+/// in a "While:" section, outermost scope first. Scopes nest, and are left in
+/// reverse order as their guards go out of scope.
+///
+/// Nothing is recorded for errors created while no scope is active anywhere
+/// in the process, so code that never uses `scope!` pays only for one atomic
+/// load per error.
+///
+/// Scopes are thread local. They are not carried across an `.await` onto
+/// whichever thread resumes an asynchronous task.
+///
+/// [guard]: crate::ScopeGuard
+///
+/// # Example
+///
+/// ```
+/// # use anyhow::{bail, scope, Result};
+/// #
+/// # struct Order {
+/// #     id: u64,
+/// #     items: Vec<u64>,
+/// # }
+/// #
+/// # fn reserve(_: u64) -> Result<()> {
+/// #     bail!("out of stock");
+/// # }
+/// #
+/// fn process(order: &Order) -> Result<()> {
+///     let _scope = scope!("processing order {}", order.id);
+///     for &item in &order.items {
+///         reserve(item)?;
+///     }
+///     Ok(())
+/// }
+/// #
+/// # fn main() {
+/// #     let order = Order { id: 1234, items: vec![7] };
+/// #     let error = process(&order).unwrap_err();
+/// #     assert!(format!("{:?}", error).contains("While:\n    processing order 1234"));
+/// # }
+/// ```
+///
+/// ```console
+/// Error: out of stock
+///
+/// While:
+///     processing order 1234
+/// ```
+#[cfg(feature = "std")]
+#[cfg_attr(doc_cfg, doc(cfg(feature = "std")))]
+#[macro_export]
+macro_rules! scope {
+    ($($arg:tt)+) => {
+        $crate::__private::enter_scope($crate::__private::format!($($arg)+))
+    };
+}
+
 /// Construct an ad-hoc error from a string or existing non-`anyhow` error
 /// value.
 ///
@@ -191,7 +444,7 @@ macro_rules! ensure {
 macro_rules! anyhow {
     ($msg:literal $(,)?) => {
         $crate::__private::must_use({
-            let error = $crate::__private::format_err($crate::__private::format_args!($msg));
+            let error = $crate::__private::format_err($msg, $crate::__private::format_args!($msg));
             error
         })
     };
@@ -205,7 +458,10 @@ macro_rules! anyhow {
         })
     };
     ($fmt:expr, $($arg:tt)*) => {
-        $crate::Error::msg($crate::__private::format!($fmt, $($arg)*))
+        $crate::__private::must_use({
+            let error = $crate::__format_err!($fmt, [] [] [] $($arg)*);
+            error
+        })
     };
 }
 
@@ -216,7 +472,7 @@ macro_rules! anyhow {
 #[macro_export]
 macro_rules! __anyhow {
     ($msg:literal $(,)?) => ({
-        let error = $crate::__private::format_err($crate::__private::format_args!($msg));
+        let error = $crate::__private::format_err($msg, $crate::__private::format_args!($msg));
         error
     });
     ($err:expr $(,)?) => ({
@@ -227,6 +483,50 @@ macro_rules! __anyhow {
         error
     });
     ($fmt:expr, $($arg:tt)*) => {
-        $crate::Error::msg($crate::__private::format!($fmt, $($arg)*))
+        $crate::__format_err!($fmt, [] [] [] $($arg)*)
+    };
+}
+
+// Not public API. Binds each argument of anyhow!($fmt, $($arg)*) to a local
+// once, so that its value can be recorded in the FormattedMessage as well as
+// interpolated into the message.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __format_err {
+    ($fmt:expr, [$($args:tt)*] [$($positional:expr,)*] [$($named:expr,)*]) => {
+        $crate::__private::format_err_interpolated(
+            $fmt,
+            $crate::__private::format_args!($fmt $($args)*),
+            $crate::__private::vec![$($positional),*],
+            $crate::__private::vec![$($named),*],
+        )
+    };
+    ($fmt:expr, [$($args:tt)*] [$($positional:expr,)*] [$($named:expr,)*] $name:ident = $value:expr $(, $($rest:tt)*)?) => {
+        match &$value {
+            value => {
+                use $crate::__private::args::*;
+                $crate::__format_err!(
+                    $fmt,
+                    [$($args)*, $name = *value]
+                    [$($positional,)*]
+                    [$($named,)* ($crate::__private::stringify!($name), (value,).__format_arg()),]
+                    $($($rest)*)?
+                )
+            }
+        }
+    };
+    ($fmt:expr, [$($args:tt)*] [$($positional:expr,)*] [$($named:expr,)*] $value:expr $(, $($rest:tt)*)?) => {
+        match &$value {
+            value => {
+                use $crate::__private::args::*;
+                $crate::__format_err!(
+                    $fmt,
+                    [$($args)*, *value]
+                    [$($positional,)* (value,).__format_arg(),]
+                    [$($named,)*]
+                    $($($rest)*)?
+                )
+            }
+        }
     };
 }
\ No newline at end of file
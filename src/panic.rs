@@ -0,0 +1,210 @@
+use crate::backtrace::Backtrace;
+use crate::{Error, Panic, Result};
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::any::Any;
+use core::fmt::{self, Display};
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use std::panic::{self, UnwindSafe};
+use std::sync::{Mutex, Once};
+use std::thread::{self, JoinHandle, ThreadId};
+
+// What the panic hook saw of a panic that has not been converted into an
+// Error yet, at most one per thread.
+struct Record {
+    thread: ThreadId,
+    message: Option<String>,
+    name: Option<String>,
+    location: Option<(String, u32, u32)>,
+    backtrace: Option<Backtrace>,
+}
+
+// Panics that are caught some other way are never taken out of here, so old
+// records are dropped once there are this many.
+const MAX_RECORDS: usize = 64;
+
+// Allocated on first use and never freed.
+static RECORDS: AtomicPtr<Mutex<Vec<Record>>> = AtomicPtr::new(ptr::null_mut());
+
+fn records() -> &'static Mutex<Vec<Record>> {
+    let mut records = RECORDS.load(Ordering::Acquire);
+    if records.is_null() {
+        let new = Box::into_raw(Box::new(Mutex::new(Vec::new())));
+        records = match RECORDS.compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => new,
+            Err(current) => {
+                drop(unsafe { Box::from_raw(new) });
+                current
+            }
+        };
+    }
+    unsafe { &*records }
+}
+
+pub(crate) fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(|location| {
+                (
+                    location.file().to_string(),
+                    location.line(),
+                    location.column(),
+                )
+            });
+            record(Record {
+                thread: thread::current().id(),
+                message: message(info.payload()).map(str::to_string),
+                name: thread::current().name().map(str::to_string),
+                location,
+                backtrace: backtrace!(),
+            });
+            previous(info);
+        }));
+    });
+}
+
+fn record(record: Record) {
+    let mut records = match records().lock() {
+        Ok(records) => records,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    records.retain(|existing| existing.thread != record.thread);
+    if records.len() == MAX_RECORDS {
+        records.remove(0);
+    }
+    records.push(record);
+}
+
+// Takes the record of the most recent panic on the given thread, as long as
+// it is the panic that this payload came from.
+fn take(thread: ThreadId, message: Option<&str>) -> Option<Record> {
+    let mut records = match records().lock() {
+        Ok(records) => records,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let index = records.iter().position(|record| {
+        record.thread == thread && record.message.as_ref().map(String::as_str) == message
+    })?;
+    Some(records.remove(index))
+}
+
+fn message(payload: &(dyn Any + Send)) -> Option<&str> {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        Some(message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        Some(message)
+    } else {
+        None
+    }
+}
+
+pub(crate) fn recover(
+    payload: Box<dyn Any + Send>,
+    thread: ThreadId,
+    name: Option<&str>,
+) -> (Panic, Option<Backtrace>) {
+    let message = message(&*payload);
+    match take(thread, message) {
+        Some(record) => {
+            let panic = Panic {
+                message: record.message,
+                thread: record.name,
+                location: record.location,
+            };
+            (panic, record.backtrace)
+        }
+        None => {
+            let panic = Panic {
+                message: message.map(str::to_string),
+                thread: name.map(str::to_string),
+                location: None,
+            };
+            (panic, None)
+        }
+    }
+}
+
+impl Panic {
+    /// The message that was passed to `panic!`, if the payload was a string.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_ref().map(String::as_str)
+    }
+
+    /// The name of the thread that panicked, if it had one.
+    pub fn thread(&self) -> Option<&str> {
+        self.thread.as_ref().map(String::as_str)
+    }
+
+    /// The source file of the `panic!`, if it was recorded by the panic hook.
+    pub fn file(&self) -> Option<&str> {
+        self.location.as_ref().map(|location| location.0.as_str())
+    }
+
+    /// The line of the `panic!`, if it was recorded by the panic hook.
+    pub fn line(&self) -> Option<u32> {
+        self.location.as_ref().map(|location| location.1)
+    }
+
+    /// The column of the `panic!`, if it was recorded by the panic hook.
+    pub fn column(&self) -> Option<u32> {
+        self.location.as_ref().map(|location| location.2)
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(thread) = &self.thread {
+            write!(f, "thread '{}' ", thread)?;
+        }
+        f.write_str("panicked")?;
+        if let Some((file, line, column)) = &self.location {
+            write!(f, " at {}:{}:{}", file, line, column)?;
+        }
+        match &self.message {
+            Some(message) => write!(f, ": {}", message),
+            None => f.write_str(": Box<dyn Any>"),
+        }
+    }
+}
+
+impl std::error::Error for Panic {}
+
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub(crate) fn catch_unwind<F, R>(f: F) -> Result<R>
+where
+    F: FnOnce() -> R + UnwindSafe,
+{
+    match panic::catch_unwind(f) {
+        Ok(value) => Ok(value),
+        Err(payload) => Err(Error::from_thread_panic(payload, &thread::current())),
+    }
+}
+
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub(crate) fn join<T>(handle: JoinHandle<T>) -> Result<T> {
+    let thread = handle.thread().clone();
+    match handle.join() {
+        Ok(value) => Ok(value),
+        Err(payload) => Err(Error::from_thread_panic(payload, &thread)),
+    }
+}
+
+// Without a backtrace from the panic hook, the next best thing is the stack
+// at which the panic was caught.
+#[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+pub(crate) fn into_error(panic: Panic, backtrace: Option<Backtrace>) -> Error {
+    let backtrace = match backtrace {
+        Some(backtrace) => Some(backtrace),
+        None => backtrace!(),
+    };
+    Error::from_std(panic, backtrace)
+}
@@ -0,0 +1,333 @@
+use crate::{Error, Redacted, Redactor, Sensitive};
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+use core::cmp;
+use core::fmt::{self, Debug, Display, Write};
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+const REDACTED: &str = "<redacted>";
+
+thread_local! {
+    // Whether an error is being rendered by Error::redacted on this thread.
+    static REDACTING: Cell<bool> = Cell::new(false);
+
+    // While anyhow! is formatting a message on this thread, the length of the
+    // message so far and the byte ranges that Sensitive values wrote to.
+    static RECORDING: RefCell<Option<Recording>> = RefCell::new(None);
+}
+
+struct Recording {
+    len: usize,
+    spans: Vec<(usize, usize)>,
+}
+
+pub(crate) enum Pattern {
+    Literal(String),
+    After(String),
+    Word(Box<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl Redactor {
+    /// Redact every occurrence of `text`.
+    pub fn literal<S>(text: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Redactor {
+            pattern: Pattern::Literal(text.into()),
+        }
+    }
+
+    /// Redact the word that follows each occurrence of `prefix`, such as the
+    /// token in `Authorization: Bearer <token>` for the prefix `"Bearer "`,
+    /// or the value in `password=<value>` for the prefix `"password="`.
+    pub fn after<S>(prefix: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Redactor {
+            pattern: Pattern::After(prefix.into()),
+        }
+    }
+
+    /// Redact every word for which `predicate` returns true, such as words
+    /// that contain an `@` for email addresses, or words that start with `/`
+    /// for absolute paths.
+    ///
+    /// Words are separated by whitespace, quotes, brackets, commas and
+    /// semicolons. A colon or period at the end of a word is not part of it.
+    pub fn word<F>(predicate: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        Redactor {
+            pattern: Pattern::Word(Box::new(predicate)),
+        }
+    }
+
+    // Adds the byte ranges of `message` that this redactor covers to `spans`.
+    fn find(&self, message: &str, spans: &mut Vec<(usize, usize)>) {
+        match &self.pattern {
+            Pattern::Literal(text) => {
+                if !text.is_empty() {
+                    for (start, _) in message.match_indices(text.as_str()) {
+                        spans.push((start, start + text.len()));
+                    }
+                }
+            }
+            Pattern::After(prefix) => {
+                if !prefix.is_empty() {
+                    for (index, _) in message.match_indices(prefix.as_str()) {
+                        let start = index + prefix.len();
+                        let rest = &message[start..];
+                        let word =
+                            trim_word(&rest[..rest.find(is_delimiter).unwrap_or(rest.len())]);
+                        if !word.is_empty() {
+                            spans.push((start, start + word.len()));
+                        }
+                    }
+                }
+            }
+            Pattern::Word(predicate) => {
+                let mut start = 0;
+                for (index, ch) in message.char_indices().chain(Some((message.len(), ' '))) {
+                    if is_delimiter(ch) {
+                        let word = trim_word(&message[start..index]);
+                        if !word.is_empty() && predicate(word) {
+                            spans.push((start, start + word.len()));
+                        }
+                        start = index + ch.len_utf8();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn is_delimiter(ch: char) -> bool {
+    ch.is_whitespace() || "\"'`()[]{}<>,;".contains(ch)
+}
+
+fn trim_word(word: &str) -> &str {
+    word.trim_end_matches(|ch| ch == ':' || ch == '.')
+}
+
+// Registered redactors form a singly linked list which is only ever pushed
+// onto, so it can be read without locking. Nodes are never freed.
+struct Node {
+    redactor: Redactor,
+    next: *const Node,
+}
+
+static REDACTORS: AtomicPtr<Node> = AtomicPtr::new(ptr::null_mut());
+
+pub(crate) fn add(redactor: Redactor) {
+    let node = Box::into_raw(Box::new(Node {
+        redactor,
+        next: ptr::null(),
+    }));
+    let mut head = REDACTORS.load(Ordering::Acquire);
+    loop {
+        unsafe { (*node).next = head };
+        match REDACTORS.compare_exchange_weak(head, node, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => return,
+            Err(current) => head = current,
+        }
+    }
+}
+
+pub(crate) fn is_redacting() -> bool {
+    REDACTING.try_with(Cell::get).unwrap_or(false)
+}
+
+// Runs `f` with Sensitive values hidden and the registered redactors applied
+// to every message that anyhow renders.
+pub(crate) fn redacting<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let _ = REDACTING.try_with(|redacting| redacting.set(self.0));
+        }
+    }
+
+    let _restore = Restore(REDACTING.with(|redacting| redacting.replace(true)));
+    f()
+}
+
+// Formats the message of anyhow!, taking note of where Sensitive values were
+// written to it so that FormattedMessage can hide them later.
+pub(crate) fn format(args: fmt::Arguments) -> (String, Vec<(usize, usize)>) {
+    // Messages that are formatted while formatting this one, by a Display
+    // impl that uses anyhow! itself, keep their own record.
+    struct Restore(Option<Recording>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let outer = self.0.take();
+            let _ = RECORDING.try_with(|recording| *recording.borrow_mut() = outer);
+        }
+    }
+
+    struct Recorder(String);
+
+    impl Write for Recorder {
+        fn write_str(&mut self, s: &str) -> fmt::Result {
+            self.0.push_str(s);
+            let len = self.0.len();
+            RECORDING.with(|recording| {
+                if let Some(recording) = &mut *recording.borrow_mut() {
+                    recording.len = len;
+                }
+            });
+            Ok(())
+        }
+    }
+
+    let recording = Recording {
+        len: 0,
+        spans: Vec::new(),
+    };
+    let _restore = Restore(RECORDING.with(|current| current.replace(Some(recording))));
+
+    let mut recorder = Recorder(String::new());
+    recorder
+        .write_fmt(args)
+        .expect("a formatting trait implementation returned an error");
+
+    let spans = RECORDING.with(|recording| match &mut *recording.borrow_mut() {
+        Some(recording) => mem::replace(&mut recording.spans, Vec::new()),
+        None => Vec::new(),
+    });
+    (recorder.0, spans)
+}
+
+// Writes `message` with the byte ranges in `spans` replaced by "<redacted>".
+// Overlapping and adjacent ranges are replaced as one.
+pub(crate) fn substitute(
+    f: &mut fmt::Formatter,
+    message: &str,
+    mut spans: Vec<(usize, usize)>,
+) -> fmt::Result {
+    // The message of a FormattedMessage can be changed through
+    // downcast_mut::<String> after its spans were recorded. Keep every range
+    // within the message, widened to whole characters.
+    for span in &mut spans {
+        span.0 = cmp::min(span.0, message.len());
+        while !message.is_char_boundary(span.0) {
+            span.0 -= 1;
+        }
+        span.1 = cmp::min(span.1, message.len());
+        while !message.is_char_boundary(span.1) {
+            span.1 += 1;
+        }
+    }
+    spans.retain(|(start, end)| start < end);
+
+    spans.sort_unstable();
+    let mut merged: Vec<(usize, usize)> = Vec::new();
+    for (start, end) in spans {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = cmp::max(last.1, end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let mut written = 0;
+    for (start, end) in merged {
+        f.write_str(&message[written..start])?;
+        f.write_str(REDACTED)?;
+        written = end;
+    }
+    f.write_str(&message[written..])
+}
+
+// Writes one message of an error that is being rendered by Error::redacted,
+// with the registered redactors applied to it.
+pub(crate) fn write(f: &mut fmt::Formatter, args: fmt::Arguments) -> fmt::Result {
+    let mut message = String::new();
+    message.write_fmt(args)?;
+
+    let mut spans = Vec::new();
+    let mut node = REDACTORS.load(Ordering::Acquire) as *const Node;
+    while let Some(current) = unsafe { node.as_ref() } {
+        current.redactor.find(&message, &mut spans);
+        node = current.next;
+    }
+
+    substitute(f, &message, spans)
+}
+
+// Writes a Sensitive value: in the clear while anyhow! is formatting a message,
+// noting where it went, and otherwise in the clear unless redacting.
+fn write_sensitive<F>(f: &mut fmt::Formatter, write: F) -> fmt::Result
+where
+    F: FnOnce(&mut fmt::Formatter) -> fmt::Result,
+{
+    let recording_len = || {
+        RECORDING
+            .try_with(|recording| recording.borrow().as_ref().map(|recording| recording.len))
+            .unwrap_or(None)
+    };
+
+    match recording_len() {
+        Some(start) => {
+            write(f)?;
+            if let Some(end) = recording_len() {
+                if end > start {
+                    RECORDING.with(|recording| {
+                        if let Some(recording) = &mut *recording.borrow_mut() {
+                            recording.spans.push((start, end));
+                        }
+                    });
+                }
+            }
+            Ok(())
+        }
+        None if is_redacting() => f.pad(REDACTED),
+        None => write(f),
+    }
+}
+
+impl<T> Display for Sensitive<T>
+where
+    T: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_sensitive(f, |f| Display::fmt(&self.0, f))
+    }
+}
+
+impl<T> Debug for Sensitive<T>
+where
+    T: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_sensitive(f, |f| Debug::fmt(&self.0, f))
+    }
+}
+
+impl<'a> Redacted<'a> {
+    pub(crate) fn new(error: &'a Error) -> Self {
+        Redacted { error }
+    }
+}
+
+impl Display for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redacting(|| Display::fmt(self.error, f))
+    }
+}
+
+impl Debug for Redacted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        redacting(|| Debug::fmt(self.error, f))
+    }
+}
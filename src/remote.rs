@@ -0,0 +1,149 @@
+use crate::{RemoteBacktrace, Report, ReportFrame, ReportLayer, StdError};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt::{self, Debug, Display};
+use core::mem;
+
+// An error that was deserialized from a Report. This is the outermost layer
+// of its cause chain and carries the remote backtrace. All of the layers are
+// kept in one slice rather than each boxed inside the one above it, so that a
+// report with a very long chain cannot overflow the stack when the error is
+// dropped.
+pub(crate) struct RemoteError {
+    layers: Box<[RemoteLayer]>,
+    pub(crate) backtrace: Option<RemoteBacktrace>,
+}
+
+// One layer of the cause chain. The layer below it is the next element of the
+// slice that holds it, which `source` reaches by pointer arithmetic.
+struct RemoteLayer {
+    layer: ReportLayer,
+    last: bool,
+}
+
+impl RemoteError {
+    pub(crate) fn new(report: Report) -> Self {
+        let Report {
+            message,
+            mut layers,
+            frames,
+        } = report;
+
+        // A report always has at least one layer unless it was put together
+        // by hand. Fall back to the outermost message.
+        if layers.is_empty() {
+            layers.push(ReportLayer {
+                debug: format!("{:?}", message),
+                display: message,
+                type_name: None,
+            });
+        }
+
+        let count = layers.len();
+        let layers: Vec<RemoteLayer> = layers
+            .into_iter()
+            .enumerate()
+            .map(|(index, layer)| RemoteLayer {
+                layer,
+                last: index + 1 == count,
+            })
+            .collect();
+
+        RemoteError {
+            layers: layers.into_boxed_slice(),
+            backtrace: if frames.is_empty() {
+                None
+            } else {
+                Some(RemoteBacktrace { frames })
+            },
+        }
+    }
+
+    // The type name of the original error behind whichever of these layers
+    // is `error`.
+    pub(crate) fn type_name_of(&self, error: &(dyn StdError + 'static)) -> Option<&str> {
+        let target = error as *const dyn StdError as *const ();
+        if self as *const RemoteError as *const () == target {
+            return self.layers[0].layer.type_name();
+        }
+        let start = self.layers.as_ptr() as usize;
+        let offset = (target as usize).wrapping_sub(start);
+        if offset % mem::size_of::<RemoteLayer>() != 0 {
+            return None;
+        }
+        self.layers
+            .get(offset / mem::size_of::<RemoteLayer>())
+            .and_then(|layer| layer.layer.type_name())
+    }
+}
+
+impl Debug for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.layers[0].layer.debug())
+    }
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.layers[0].layer.display())
+    }
+}
+
+impl StdError for RemoteError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.layers[0].source()
+    }
+}
+
+impl Debug for RemoteLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.layer.debug())
+    }
+}
+
+impl Display for RemoteLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.layer.display())
+    }
+}
+
+impl StdError for RemoteLayer {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        if self.last {
+            None
+        } else {
+            // Safety: every RemoteLayer is an element of the slice of a
+            // RemoteError, and one that is not the last is followed by another.
+            Some(unsafe { &*(self as *const RemoteLayer).add(1) })
+        }
+    }
+}
+
+impl RemoteBacktrace {
+    /// The resolved frames of the backtrace, innermost call first.
+    pub fn frames(&self) -> &[ReportFrame] {
+        &self.frames
+    }
+}
+
+impl Display for RemoteBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, frame) in self.frames.iter().enumerate() {
+            if n > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:>4}: {}", n, frame.function().unwrap_or("<unknown>"))?;
+            if let Some(file) = frame.file() {
+                write!(f, "\n             at {}", file)?;
+                if let Some(line) = frame.line() {
+                    write!(f, ":{}", line)?;
+                    if let Some(column) = frame.column() {
+                        write!(f, ":{}", column)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,380 @@
+use crate::error::ErrorImpl;
+use crate::remote::RemoteError;
+use crate::{Error, Report, ReportFrame, ReportLayer};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+impl Report {
+    /// Take a snapshot of the error's message, cause chain and backtrace.
+    pub fn new(error: &Error) -> Self {
+        let this = error.inner.by_ref();
+        let layers = unsafe { ErrorImpl::chain(this) }
+            .map(|cause| ReportLayer {
+                display: cause.to_string(),
+                debug: format!("{:?}", cause),
+                type_name: unsafe { ErrorImpl::type_name_of(this, cause) }.map(String::from),
+            })
+            .collect();
+
+        Report {
+            message: error.to_string(),
+            layers,
+            frames: frames(error),
+        }
+    }
+
+    /// The Display representation of the outermost layer of the error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The layers of the cause chain, outermost first, as in
+    /// [`Error::chain`].
+    pub fn layers(&self) -> &[ReportLayer] {
+        &self.layers
+    }
+
+    /// The resolved backtrace frames, innermost call first.
+    ///
+    /// This is empty if no backtrace was captured, or if the backtrace
+    /// implementation in use does not expose individual frames.
+    pub fn frames(&self) -> &[ReportFrame] {
+        &self.frames
+    }
+}
+
+impl ReportLayer {
+    /// The Display representation of this layer.
+    pub fn display(&self) -> &str {
+        &self.display
+    }
+
+    /// The Debug representation of this layer.
+    pub fn debug(&self) -> &str {
+        &self.debug
+    }
+
+    /// The Rust type name of this layer, if it is a context value or error
+    /// object that anyhow was given directly.
+    ///
+    /// Causes that are only reachable through some other error's `source()`
+    /// are of an unknown type, as are errors that were boxed before being
+    /// converted into `anyhow::Error`. Remote layers keep the type name that
+    /// was reported for them.
+    pub fn type_name(&self) -> Option<&str> {
+        self.type_name.as_ref().map(AsRef::as_ref)
+    }
+}
+
+impl ReportFrame {
+    /// The demangled name of the function, without its hash.
+    pub fn function(&self) -> Option<&str> {
+        self.function.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The source file, relative to the current directory where possible.
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The 1-based line number within `file`.
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    /// The 1-based column number within `line`.
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+}
+
+#[cfg(any(std_backtrace, feature = "backtrace"))]
+fn frames(error: &Error) -> Vec<ReportFrame> {
+    error
+        .backtrace_frames()
+        .map(|frame| ReportFrame {
+            function: frame.name().map(String::from),
+            file: frame.file().map(|file| file.display().to_string()),
+            line: frame.line(),
+            column: frame.column(),
+        })
+        .collect()
+}
+
+#[cfg(not(any(std_backtrace, feature = "backtrace")))]
+fn frames(error: &Error) -> Vec<ReportFrame> {
+    let _ = error;
+    Vec::new()
+}
+
+impl Serialize for Error {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Report::new(self).serialize(serializer)
+    }
+}
+
+impl Serialize for Report {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut report = serializer.serialize_struct("Report", 3)?;
+        report.serialize_field("message", self.message())?;
+        report.serialize_field("chain", self.layers())?;
+        report.serialize_field("backtrace", self.frames())?;
+        report.end()
+    }
+}
+
+impl Serialize for ReportLayer {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut layer = serializer.serialize_struct("ReportLayer", 3)?;
+        layer.serialize_field("display", self.display())?;
+        layer.serialize_field("debug", self.debug())?;
+        layer.serialize_field("type_name", &self.type_name())?;
+        layer.end()
+    }
+}
+
+impl Serialize for ReportFrame {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut frame = serializer.serialize_struct("ReportFrame", 4)?;
+        frame.serialize_field("function", &self.function())?;
+        frame.serialize_field("file", &self.file())?;
+        frame.serialize_field("line", &self.line)?;
+        frame.serialize_field("column", &self.column)?;
+        frame.end()
+    }
+}
+
+impl From<Report> for Error {
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn from(report: Report) -> Self {
+        Error::from_remote(RemoteError::new(report), backtrace!())
+    }
+}
+
+impl<'de> Deserialize<'de> for Error {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Report::deserialize(deserializer).map(Error::from)
+    }
+}
+
+impl<'de> Deserialize<'de> for Report {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const FIELDS: &[&str] = &["message", "chain", "backtrace"];
+        deserializer.deserialize_struct("Report", FIELDS, ReportVisitor)
+    }
+}
+
+struct ReportVisitor;
+
+impl<'de> Visitor<'de> for ReportVisitor {
+    type Value = Report;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an error report")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Report, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let message = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let layers = seq.next_element()?.unwrap_or_default();
+        let frames = seq.next_element()?.unwrap_or_default();
+        check_message(Report {
+            message,
+            layers,
+            frames,
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Report, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut message = None;
+        let mut layers = None;
+        let mut frames = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "message" => set_once(&mut message, "message", map.next_value()?)?,
+                "chain" => set_once(&mut layers, "chain", map.next_value()?)?,
+                "backtrace" => set_once(&mut frames, "backtrace", map.next_value()?)?,
+                _ => drop(map.next_value::<IgnoredAny>()?),
+            }
+        }
+        check_message(Report {
+            message: message.ok_or_else(|| de::Error::missing_field("message"))?,
+            layers: layers.unwrap_or_default(),
+            frames: frames.unwrap_or_default(),
+        })
+    }
+}
+
+// The message of a report is the Display representation of the outermost
+// layer, which is what the error displays once the report is turned back into
+// one. A report whose message says something else is rejected rather than
+// having one or the other silently win.
+fn check_message<E>(report: Report) -> Result<Report, E>
+where
+    E: de::Error,
+{
+    match report.layers.first() {
+        Some(outermost) if outermost.display != report.message => Err(E::invalid_value(
+            de::Unexpected::Str(&report.message),
+            &"the display of the outermost layer of the chain",
+        )),
+        _ => Ok(report),
+    }
+}
+
+fn set_once<T, E>(field: &mut Option<T>, name: &'static str, value: T) -> Result<(), E>
+where
+    E: de::Error,
+{
+    if field.is_some() {
+        return Err(E::duplicate_field(name));
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+impl<'de> Deserialize<'de> for ReportLayer {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const FIELDS: &[&str] = &["display", "debug", "type_name"];
+        deserializer.deserialize_struct("ReportLayer", FIELDS, ReportLayerVisitor)
+    }
+}
+
+struct ReportLayerVisitor;
+
+impl<'de> Visitor<'de> for ReportLayerVisitor {
+    type Value = ReportLayer;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a layer of an error report")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<ReportLayer, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let display: String = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let debug = seq.next_element()?.unwrap_or_else(|| display.clone());
+        let type_name = seq.next_element()?.unwrap_or_default();
+        Ok(ReportLayer {
+            display,
+            debug,
+            type_name,
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<ReportLayer, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut display: Option<String> = None;
+        let mut debug = None;
+        let mut type_name = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "display" => set_once(&mut display, "display", map.next_value()?)?,
+                "debug" => set_once(&mut debug, "debug", map.next_value()?)?,
+                "type_name" => set_once(&mut type_name, "type_name", map.next_value()?)?,
+                _ => drop(map.next_value::<IgnoredAny>()?),
+            }
+        }
+        let display = display.ok_or_else(|| de::Error::missing_field("display"))?;
+        Ok(ReportLayer {
+            debug: debug.unwrap_or_else(|| display.clone()),
+            display,
+            type_name: type_name.unwrap_or_default(),
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for ReportFrame {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const FIELDS: &[&str] = &["function", "file", "line", "column"];
+        deserializer.deserialize_struct("ReportFrame", FIELDS, ReportFrameVisitor)
+    }
+}
+
+struct ReportFrameVisitor;
+
+impl<'de> Visitor<'de> for ReportFrameVisitor {
+    type Value = ReportFrame;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a backtrace frame of an error report")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<ReportFrame, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        Ok(ReportFrame {
+            function: seq.next_element()?.unwrap_or_default(),
+            file: seq.next_element()?.unwrap_or_default(),
+            line: seq.next_element()?.unwrap_or_default(),
+            column: seq.next_element()?.unwrap_or_default(),
+        })
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<ReportFrame, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut function = None;
+        let mut file = None;
+        let mut line = None;
+        let mut column = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "function" => set_once(&mut function, "function", map.next_value()?)?,
+                "file" => set_once(&mut file, "file", map.next_value()?)?,
+                "line" => set_once(&mut line, "line", map.next_value()?)?,
+                "column" => set_once(&mut column, "column", map.next_value()?)?,
+                _ => drop(map.next_value::<IgnoredAny>()?),
+            }
+        }
+        Ok(ReportFrame {
+            function: function.unwrap_or_default(),
+            file: file.unwrap_or_default(),
+            line: line.unwrap_or_default(),
+            column: column.unwrap_or_default(),
+        })
+    }
+}
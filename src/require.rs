@@ -0,0 +1,85 @@
+use crate::context::ext::StdError;
+use crate::Error;
+use alloc::format;
+
+// The Option or Result unwrapped by require!, and what it held instead of a
+// value.
+#[doc(hidden)]
+pub trait Require {
+    type Value;
+    type Missing: Missing;
+
+    fn __require(self) -> Result<Self::Value, Self::Missing>;
+}
+
+#[doc(hidden)]
+pub trait Missing {
+    fn __into_error(self, expression: &'static str) -> Error;
+    fn __with_message(self, message: Error) -> Error;
+}
+
+impl<T> Require for Option<T> {
+    type Value = T;
+    type Missing = NoneValue;
+
+    fn __require(self) -> Result<T, NoneValue> {
+        match self {
+            Some(value) => Ok(value),
+            None => Err(NoneValue),
+        }
+    }
+}
+
+impl<T, E> Require for Result<T, E>
+where
+    E: StdError + Send + Sync + 'static,
+    Error: From<E>,
+{
+    type Value = T;
+    type Missing = ErrValue<E>;
+
+    fn __require(self) -> Result<T, ErrValue<E>> {
+        match self {
+            Ok(value) => Ok(value),
+            Err(error) => Err(ErrValue(error)),
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct NoneValue;
+
+impl Missing for NoneValue {
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn __into_error(self, expression: &'static str) -> Error {
+        Error::msg(format!("`{}` is None", expression))
+    }
+
+    #[cold]
+    fn __with_message(self, message: Error) -> Error {
+        message
+    }
+}
+
+#[doc(hidden)]
+pub struct ErrValue<E>(E);
+
+impl<E> Missing for ErrValue<E>
+where
+    E: StdError + Send + Sync + 'static,
+    Error: From<E>,
+{
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn __into_error(self, _expression: &'static str) -> Error {
+        Error::from(self.0)
+    }
+
+    // The error becomes the source of the message, like with Context.
+    #[cold]
+    #[cfg_attr(not(anyhow_no_track_caller), track_caller)]
+    fn __with_message(self, message: Error) -> Error {
+        self.0.ext_context(message)
+    }
+}
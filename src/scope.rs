@@ -0,0 +1,76 @@
+use crate::attachment::{Attachment, AttachmentsState};
+use crate::error::ErrorImpl;
+use crate::ptr::Ref;
+use crate::ScopeGuard;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+// Number of scopes that are currently entered, across all threads. Errors
+// constructed while this is zero skip the thread local entirely.
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static SCOPES: RefCell<Vec<Arc<str>>> = RefCell::new(Vec::new());
+}
+
+// The scopes that were entered on the thread that constructed one layer of an
+// anyhow::Error, outermost first. Stored among the layer's attachments.
+pub(crate) struct Snapshot(Vec<Arc<str>>);
+
+impl Snapshot {
+    pub fn frames(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|frame| &**frame)
+    }
+}
+
+pub fn enter(frame: String) -> ScopeGuard {
+    let depth = SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        scopes.push(Arc::from(frame));
+        scopes.len() - 1
+    });
+    ACTIVE.fetch_add(1, Ordering::Relaxed);
+    ScopeGuard {
+        depth,
+        not_send: PhantomData,
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        ACTIVE.fetch_sub(1, Ordering::Relaxed);
+        // Truncating rather than popping keeps the stack consistent even if
+        // guards are dropped in a different order than they were created.
+        let _ = SCOPES.try_with(|scopes| scopes.borrow_mut().truncate(self.depth));
+    }
+}
+
+// The attachments that a newly constructed layer starts out with: a snapshot
+// of the current scopes, if there are any.
+pub(crate) fn capture() -> Vec<Attachment> {
+    if ACTIVE.load(Ordering::Relaxed) == 0 {
+        return Vec::new();
+    }
+    let frames = SCOPES
+        .try_with(|scopes| scopes.borrow().clone())
+        .unwrap_or_default();
+    if frames.is_empty() {
+        Vec::new()
+    } else {
+        vec![Attachment::new(Snapshot(frames))]
+    }
+}
+
+// The snapshot taken by the innermost layer that was constructed inside of a
+// scope. Outer layers were built later, after the error had propagated out of
+// some of the scopes, so the innermost one is the most complete.
+pub(crate) unsafe fn innermost(this: Ref<'_, ErrorImpl>) -> Option<&Snapshot> {
+    AttachmentsState::new(this)
+        .filter_map(|attachment| attachment.downcast_ref::<Snapshot>())
+        .last()
+}
@@ -0,0 +1,76 @@
+use crate::{Error, SharedError, StdError};
+use alloc::sync::Arc;
+use core::fmt::{self, Debug, Display};
+use core::ops::Deref;
+
+#[cfg(backtrace)]
+use std::any::{Demand, Provider};
+
+impl SharedError {
+    /// Wrap an `anyhow::Error` so that it can be cloned.
+    pub fn new(error: Error) -> Self {
+        SharedError {
+            inner: Arc::new(error),
+        }
+    }
+
+    /// The underlying error shared by every clone of this `SharedError`.
+    pub fn as_error(&self) -> &Error {
+        &self.inner
+    }
+
+    /// Returns true if both handles refer to the same underlying error.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.inner, &other.inner)
+    }
+
+    /// Recover the underlying `anyhow::Error` if this is the only remaining
+    /// handle to it, otherwise give the `SharedError` back.
+    pub fn try_unwrap(this: Self) -> Result<Error, Self> {
+        Arc::try_unwrap(this.inner).map_err(|inner| SharedError { inner })
+    }
+}
+
+impl From<Error> for SharedError {
+    #[cold]
+    fn from(error: Error) -> Self {
+        SharedError::new(error)
+    }
+}
+
+impl Deref for SharedError {
+    type Target = Error;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl AsRef<Error> for SharedError {
+    fn as_ref(&self) -> &Error {
+        &self.inner
+    }
+}
+
+impl Display for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&*self.inner, f)
+    }
+}
+
+impl Debug for SharedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&*self.inner, f)
+    }
+}
+
+impl StdError for SharedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner.source()
+    }
+
+    #[cfg(backtrace)]
+    fn provide<'a>(&'a self, demand: &mut Demand<'a>) {
+        Provider::provide(&*self.inner, demand);
+    }
+}
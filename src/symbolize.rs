@@ -0,0 +1,430 @@
+// The dependencies of the "symbolize" feature require a much newer compiler
+// than the rest of the crate.
+#![allow(clippy::incompatible_msrv)]
+
+use crate::{bail, ensure, format_err};
+use crate::{BacktraceFrame, Context, Error, RawBacktrace, RawModule, Result};
+use addr2line::Loader;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Write};
+use core::ops::Range;
+use core::str::FromStr;
+use object::Object;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "anyhow raw backtrace";
+
+impl RawBacktrace {
+    pub(crate) fn capture(frames: Vec<usize>) -> Option<Self> {
+        if frames.is_empty() {
+            return None;
+        }
+
+        // Only the modules that any of the frames are in.
+        let modules = loaded_modules()
+            .into_iter()
+            .filter(|module| frames.iter().any(|&ip| module.contains(ip)))
+            .collect();
+        Some(RawBacktrace { frames, modules })
+    }
+
+    /// The instruction pointers of the frames, innermost call first.
+    pub fn frames(&self) -> &[usize] {
+        &self.frames
+    }
+
+    /// The modules containing the frames.
+    ///
+    /// Modules are currently only collected on Linux. Elsewhere this is
+    /// empty, and frames cannot be resolved.
+    pub fn modules(&self) -> &[RawModule] {
+        &self.modules
+    }
+
+    /// Symbolize the frames, using the given unstripped binaries or separate
+    /// debug files.
+    ///
+    /// Each module of the backtrace is matched to one of the files by its
+    /// build ID, or by file name if the module or the file has no build ID.
+    /// Frames in modules that have no matching file are returned with only
+    /// their instruction pointer.
+    ///
+    /// As in [`Error::backtrace_frames`], a frame of the call stack resolves
+    /// to several [`BacktraceFrame`]s when functions were inlined into it.
+    pub fn resolve<P>(&self, debug_files: &[P]) -> Result<Vec<BacktraceFrame>>
+    where
+        P: AsRef<Path>,
+    {
+        let mut files = Vec::new();
+        for path in debug_files {
+            files.push(DebugFile::open(path.as_ref())?);
+        }
+
+        let matches: Vec<Option<&DebugFile>> = self
+            .modules
+            .iter()
+            .map(|module| files.iter().find(|file| file.matches(module)))
+            .collect();
+
+        let mut frames = Vec::new();
+        for &ip in &self.frames {
+            let found = self
+                .modules
+                .iter()
+                .zip(&matches)
+                .find(|(module, _)| module.contains(ip));
+            match found {
+                Some((module, Some(file))) => file.resolve(ip, module, &mut frames),
+                _ => frames.push(unresolved(ip)),
+            }
+        }
+        Ok(frames)
+    }
+}
+
+impl Display for RawBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(HEADER)?;
+        for module in &self.modules {
+            write!(
+                f,
+                "\nmodule {:#x}-{:#x} base {:#x} build-id ",
+                module.start, module.end, module.base,
+            )?;
+            match &module.build_id {
+                Some(build_id) => {
+                    for byte in build_id {
+                        write!(f, "{:02x}", byte)?;
+                    }
+                }
+                None => f.write_char('-')?,
+            }
+            write!(f, " {}", module.path.display())?;
+        }
+        for ip in &self.frames {
+            write!(f, "\nframe {:#x}", ip)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RawBacktrace {
+    type Err = Error;
+
+    fn from_str(dump: &str) -> Result<Self> {
+        let mut lines = dump.lines().map(str::trim).filter(|line| !line.is_empty());
+        if lines.next() != Some(HEADER) {
+            bail!("not a raw backtrace: expected {:?} on the first line", HEADER);
+        }
+
+        let mut raw = RawBacktrace {
+            frames: Vec::new(),
+            modules: Vec::new(),
+        };
+        for line in lines {
+            let mut words = line.splitn(2, ' ');
+            match (words.next(), words.next()) {
+                (Some("module"), Some(rest)) => {
+                    let module = parse_module(rest)
+                        .with_context(|| format!("invalid module line: {}", line))?;
+                    raw.modules.push(module);
+                }
+                (Some("frame"), Some(ip)) => {
+                    let ip = parse_address(ip)
+                        .with_context(|| format!("invalid frame line: {}", line))?;
+                    raw.frames.push(ip);
+                }
+                _ => bail!("unrecognized line in raw backtrace: {}", line),
+            }
+        }
+        Ok(raw)
+    }
+}
+
+impl RawModule {
+    /// The path of the module in the process that captured the backtrace.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The addresses that the module was loaded at.
+    pub fn address_range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// The difference between the addresses in the process and the
+    /// addresses in the module's file.
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    /// The GNU build ID of the module, if it has one.
+    pub fn build_id(&self) -> Option<&[u8]> {
+        self.build_id.as_ref().map(AsRef::as_ref)
+    }
+
+    fn contains(&self, ip: usize) -> bool {
+        self.start <= ip && ip < self.end
+    }
+}
+
+// "0x1000-0x2000 base 0x1000 build-id 0123abcd /path/to/module", where the
+// path may contain spaces.
+fn parse_module(line: &str) -> Result<RawModule> {
+    let mut words = line.splitn(6, ' ');
+    let mut next = |what| {
+        words
+            .next()
+            .ok_or_else(|| format_err!("missing {}", what))
+    };
+
+    let range = next("address range")?;
+    let dash = range.find('-').context("missing '-' in address range")?;
+    let start = parse_address(&range[..dash])?;
+    let end = parse_address(&range[dash + 1..])?;
+
+    ensure!(next("base")? == "base", "expected base");
+    let base = parse_address(next("base")?)?;
+
+    ensure!(next("build-id")? == "build-id", "expected build-id");
+    let build_id = match next("build-id")? {
+        "-" => None,
+        hex => Some(parse_hex(hex)?),
+    };
+
+    let path = PathBuf::from(next("path")?);
+    Ok(RawModule {
+        path,
+        start,
+        end,
+        base,
+        build_id,
+    })
+}
+
+fn parse_address(word: &str) -> Result<usize> {
+    let hex = if word.starts_with("0x") {
+        &word[2..]
+    } else {
+        word
+    };
+    usize::from_str_radix(hex, 16).with_context(|| format!("invalid address {:?}", word))
+}
+
+fn parse_hex(word: &str) -> Result<Vec<u8>> {
+    ensure!(
+        word.bytes().all(|byte| byte.is_ascii_hexdigit()),
+        "invalid hex {:?}",
+        word,
+    );
+    ensure!(word.len() % 2 == 0, "odd number of digits in {:?}", word);
+    Ok(word
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| (hex_digit(pair[0]) << 4) | hex_digit(pair[1]))
+        .collect())
+}
+
+fn hex_digit(byte: u8) -> u8 {
+    match byte {
+        b'0'..=b'9' => byte - b'0',
+        b'a'..=b'f' => byte - b'a' + 10,
+        _ => byte - b'A' + 10,
+    }
+}
+
+fn unresolved(ip: usize) -> BacktraceFrame {
+    BacktraceFrame {
+        ip: Some(ip),
+        name: None,
+        file: None,
+        line: None,
+        column: None,
+        inlined: false,
+    }
+}
+
+fn demangle(name: &str) -> String {
+    match rustc_demangle::try_demangle(name) {
+        Ok(demangled) => format!("{:#}", demangled),
+        Err(_) => String::from(name),
+    }
+}
+
+struct DebugFile {
+    path: PathBuf,
+    build_id: Option<Vec<u8>>,
+    loader: Loader,
+}
+
+impl DebugFile {
+    fn open(path: &Path) -> Result<Self> {
+        let data =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let object = object::File::parse(&*data)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        let build_id = match object.build_id() {
+            Ok(build_id) => build_id.map(Vec::from),
+            Err(_) => None,
+        };
+        let loader = Loader::new(path)
+            .map_err(|error| Error::msg(error.to_string()))
+            .with_context(|| format!("failed to load debug info from {}", path.display()))?;
+        Ok(DebugFile {
+            path: path.to_owned(),
+            build_id,
+            loader,
+        })
+    }
+
+    fn matches(&self, module: &RawModule) -> bool {
+        match (&module.build_id, &self.build_id) {
+            (Some(module), Some(file)) => module == file,
+            _ => module.path.file_name() == self.path.file_name(),
+        }
+    }
+
+    fn resolve(&self, ip: usize, module: &RawModule, frames: &mut Vec<BacktraceFrame>) {
+        // Instruction pointers of a backtrace are return addresses, which
+        // may already belong to the next line, or the next function.
+        let probe = ip.wrapping_sub(1).wrapping_sub(module.base) as u64;
+
+        let first = frames.len();
+        if let Ok(mut iter) = self.loader.find_frames(probe) {
+            while let Ok(Some(frame)) = iter.next() {
+                let name = frame
+                    .function
+                    .as_ref()
+                    .and_then(|function| function.raw_name().ok())
+                    .map(|name| demangle(&name));
+                let location = frame.location.as_ref();
+                frames.push(BacktraceFrame {
+                    ip: Some(ip),
+                    name,
+                    file: location.and_then(|l| l.file).map(PathBuf::from),
+                    line: location.and_then(|l| l.line),
+                    column: location.and_then(|l| l.column),
+                    inlined: true,
+                });
+            }
+        }
+
+        if frames.len() == first {
+            // No debug info for this address. The symbol table may still
+            // know the function.
+            let mut frame = unresolved(ip);
+            frame.name = self.loader.find_symbol(probe).map(demangle);
+            frames.push(frame);
+        }
+
+        // Every function except the outermost was inlined into it.
+        frames.last_mut().unwrap().inlined = false;
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn loaded_modules() -> Vec<RawModule> {
+    use core::slice;
+    use std::ffi::{CStr, OsStr};
+    use std::os::unix::ffi::OsStrExt;
+
+    unsafe extern "C" fn callback(
+        info: *mut libc::dl_phdr_info,
+        _size: libc::size_t,
+        modules: *mut libc::c_void,
+    ) -> libc::c_int {
+        let modules = &mut *(modules as *mut Vec<RawModule>);
+        let info = &*info;
+        let base = info.dlpi_addr as usize;
+        let phdrs = slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum as usize);
+
+        let mut start = usize::MAX;
+        let mut end = 0;
+        let mut build_id = None;
+        for phdr in phdrs {
+            let address = base.wrapping_add(phdr.p_vaddr as usize);
+            let size = phdr.p_memsz as usize;
+            match phdr.p_type {
+                libc::PT_LOAD => {
+                    start = start.min(address);
+                    end = end.max(address + size);
+                }
+                libc::PT_NOTE if build_id.is_none() => {
+                    let notes = slice::from_raw_parts(address as *const u8, size);
+                    build_id = gnu_build_id(notes, phdr.p_align as usize);
+                }
+                _ => {}
+            }
+        }
+
+        if start < end {
+            // The main executable is reported with an empty name.
+            let name = if info.dlpi_name.is_null() {
+                &[][..]
+            } else {
+                CStr::from_ptr(info.dlpi_name).to_bytes()
+            };
+            let path = if name.is_empty() {
+                std::env::current_exe().unwrap_or_default()
+            } else {
+                PathBuf::from(OsStr::from_bytes(name))
+            };
+            modules.push(RawModule {
+                path,
+                start,
+                end,
+                base,
+                build_id,
+            });
+        }
+        0
+    }
+
+    let mut modules = Vec::new();
+    unsafe {
+        libc::dl_iterate_phdr(
+            Some(callback),
+            &mut modules as *mut Vec<RawModule> as *mut libc::c_void,
+        );
+    }
+    modules
+}
+
+#[cfg(not(target_os = "linux"))]
+fn loaded_modules() -> Vec<RawModule> {
+    Vec::new()
+}
+
+// The descriptor of the NT_GNU_BUILD_ID note among the notes of a PT_NOTE
+// segment, which are in the byte order of the running process.
+#[cfg(target_os = "linux")]
+fn gnu_build_id(mut notes: &[u8], align: usize) -> Option<Vec<u8>> {
+    const NT_GNU_BUILD_ID: u32 = 3;
+
+    let align = align.max(4);
+    let padded = |len: usize| (len + align - 1) / align * align;
+    let word = |bytes: &[u8]| {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[..4]);
+        u32::from_ne_bytes(word) as usize
+    };
+
+    while notes.len() >= 12 {
+        let namesz = word(&notes[0..]);
+        let descsz = word(&notes[4..]);
+        let kind = word(&notes[8..]) as u32;
+        let name_start = 12;
+        let desc_start = name_start + padded(namesz);
+        let desc_end = desc_start + descsz;
+        if desc_end > notes.len() {
+            return None;
+        }
+        if kind == NT_GNU_BUILD_ID && &notes[name_start..name_start + namesz] == b"GNU\0" {
+            return Some(notes[desc_start..desc_end].to_vec());
+        }
+        notes = &notes[(desc_start + padded(descsz)).min(notes.len())..];
+    }
+    None
+}
@@ -1,4 +1,4 @@
-use crate::StdError;
+use crate::{Errors, StdError};
 use core::fmt::{self, Debug, Display};
 
 #[cfg(backtrace)]
@@ -50,6 +50,23 @@ where
 
 impl<M> StdError for DisplayError<M> where M: Display + 'static {}
 
+#[repr(transparent)]
+pub struct AggregateError(pub Errors);
+
+impl Debug for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for AggregateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl StdError for AggregateError {}
+
 #[cfg(feature = "std")]
 #[repr(transparent)]
 pub struct BoxedError(pub Box<dyn StdError + Send + Sync>);
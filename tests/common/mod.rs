@@ -1,4 +1,7 @@
-use anyhow::{bail, Result};
+// Each test crate that includes this module uses only some of these.
+#![allow(dead_code)]
+
+use anyhow::{anyhow, bail, BacktracePolicy, Error, Result};
 use std::io;
 
 pub fn bail_literal() -> Result<()> {
@@ -12,3 +15,9 @@ pub fn bail_fmt() -> Result<()> {
 pub fn bail_error() -> Result<()> {
     bail!(io::Error::new(io::ErrorKind::Other, "oh no!"));
 }
+
+// An error with a backtrace that starts in a function other than the test.
+#[inline(never)]
+pub fn make_error() -> Error {
+    anyhow::with_backtrace_policy(BacktracePolicy::Always, || anyhow!("oh no!"))
+}
@@ -0,0 +1,98 @@
+mod drop;
+
+use crate::drop::{DetectDrop, Flag};
+use anyhow::{anyhow, Context, Error, Errors};
+use std::io;
+
+fn errors() -> Errors {
+    let mut errors = Errors::new();
+    errors.push(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    errors.push(anyhow!("oh dear").context("f failed"));
+    errors
+}
+
+#[test]
+fn test_into_result() {
+    assert!(Errors::new().into_result().is_ok());
+
+    let error = errors().into_result().unwrap_err();
+    assert_eq!("2 errors occurred", error.to_string());
+    assert_eq!(2, error.downcast_ref::<Errors>().unwrap().len());
+}
+
+#[test]
+fn test_collect() {
+    let ok = Errors::collect::<Vec<u8>, _, _, _>(["1", "2"].iter().map(|s| s.parse::<u8>()));
+    assert_eq!(vec![1, 2], ok.unwrap());
+
+    let error = Errors::collect::<Vec<u8>, _, _, _>(["1", "x"].iter().map(|s| s.parse::<u8>()))
+        .unwrap_err();
+    assert_eq!("1 error occurred", error.to_string());
+}
+
+#[test]
+fn test_combine() {
+    assert!(Errors::combine(Vec::<Error>::new()).is_ok());
+
+    let error = Errors::combine(vec![anyhow!("a"), anyhow!("b"), anyhow!("c")]).unwrap_err();
+    let messages: Vec<String> = error
+        .downcast_ref::<Errors>()
+        .unwrap()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(vec!["a", "b", "c"], messages);
+}
+
+#[test]
+fn test_downcast_into_child() {
+    let error = errors().into_result().unwrap_err();
+    assert!(error.is::<io::Error>());
+    assert_eq!(Some(&"f failed"), error.downcast_ref::<&str>());
+
+    let error = Err::<(), _>(error).context("g failed").unwrap_err();
+    assert!(error.is::<io::Error>());
+    assert!(error.is::<Errors>());
+
+    // Children can only be reached by shared reference.
+    let error = error.downcast::<io::Error>().unwrap_err();
+    let errors = error.downcast::<Errors>().unwrap();
+    assert_eq!(2, errors.len());
+}
+
+#[test]
+fn test_debug() {
+    let mut inner = Errors::new();
+    inner.push(anyhow!("x"));
+    inner.push(anyhow!("y"));
+    let mut errors = errors();
+    errors.push(inner);
+    let error = Error::from(errors);
+
+    let expected = "\
+3 errors occurred
+
+Errors:
+    0: oh no!
+    1: f failed
+       
+       Caused by:
+           oh dear
+    2: 2 errors occurred
+       
+       Errors:
+           0: x
+           1: y";
+    assert!(format!("{:?}", error).starts_with(expected));
+}
+
+#[test]
+fn test_drop() {
+    let has_dropped = Flag::new();
+    let mut errors = Errors::new();
+    errors.push(DetectDrop::new(&has_dropped));
+    let error = errors.into_result().unwrap_err();
+    assert!(!has_dropped.get());
+    drop(error);
+    assert!(has_dropped.get());
+}
@@ -0,0 +1,88 @@
+mod drop;
+
+use crate::drop::{DetectDrop, Flag};
+use anyhow::{anyhow, Context, Error, Result};
+use std::io;
+
+#[derive(Debug, PartialEq)]
+struct RequestId(u64);
+
+#[derive(Debug, PartialEq)]
+struct RetryAfter(u32);
+
+fn fail() -> Result<()> {
+    let error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    Err(Error::new(error).attach(RequestId(1)).attach(RetryAfter(30)))
+}
+
+#[test]
+fn test_attachment() {
+    let error = fail().unwrap_err();
+    assert_eq!(Some(&RequestId(1)), error.attachment::<RequestId>());
+    assert_eq!(Some(&RetryAfter(30)), error.attachment::<RetryAfter>());
+    assert!(error.attachment::<String>().is_none());
+}
+
+#[test]
+fn test_survives_context() {
+    let error = fail()
+        .context("f failed")
+        .map_err(|error| error.attach(RequestId(2)))
+        .context("g failed")
+        .unwrap_err();
+
+    let ids: Vec<&RequestId> = error.attachments::<RequestId>().collect();
+    assert_eq!(vec![&RequestId(2), &RequestId(1)], ids);
+    assert_eq!(Some(&RetryAfter(30)), error.attachment::<RetryAfter>());
+}
+
+#[test]
+fn test_most_recent_first() {
+    let error = anyhow!("oh no!").attach(RetryAfter(1)).attach(RetryAfter(2));
+    let retries: Vec<&RetryAfter> = error.attachments::<RetryAfter>().collect();
+    assert_eq!(vec![&RetryAfter(2), &RetryAfter(1)], retries);
+}
+
+#[test]
+fn test_display_unaffected() {
+    let error = fail()
+        .context("f failed")
+        .unwrap_err()
+        .attach_printable("request 1");
+    assert_eq!("f failed", error.to_string());
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+}
+
+#[test]
+fn test_debug() {
+    let error = fail()
+        .context("f failed")
+        .unwrap_err()
+        .attach_printable("request 1");
+    let expected = "\
+f failed
+
+Caused by:
+    oh no!
+
+Attachments:
+    request 1";
+    assert!(format!("{:?}", error).starts_with(expected));
+}
+
+#[test]
+fn test_drop() {
+    let has_dropped = Flag::new();
+    let error = anyhow!("oh no!").attach(DetectDrop::new(&has_dropped));
+    drop(error);
+    assert!(has_dropped.get());
+}
+
+#[test]
+fn test_drop_after_downcast() {
+    let has_dropped = Flag::new();
+    let error = anyhow!("oh no!").attach(DetectDrop::new(&has_dropped));
+    let message = error.downcast::<&str>().unwrap();
+    assert_eq!("oh no!", message);
+    assert!(has_dropped.get());
+}
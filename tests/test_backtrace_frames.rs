@@ -0,0 +1,29 @@
+#![cfg(feature = "backtrace")]
+
+mod common;
+
+use self::common::make_error;
+use std::path::Path;
+
+#[test]
+fn test_backtrace_frames() {
+    let error = make_error();
+    let frames: Vec<_> = error.backtrace_frames().collect();
+    assert_eq!(frames.len(), error.backtrace_frames().len());
+
+    let frame = frames
+        .iter()
+        .find(|frame| {
+            frame
+                .name()
+                .map_or(false, |name| name.ends_with("::test_backtrace_frames"))
+        })
+        .unwrap_or_else(|| panic!("{:#?}", frames));
+    assert!(frame.ip().is_some());
+    assert!(frame.line().is_some());
+    let file = frame.file().unwrap();
+    assert_eq!(file.file_name(), Path::new(file!()).file_name());
+
+    // Only the last symbol of each stack frame is a real call.
+    assert!(!frames.last().unwrap().is_inlined());
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "backtrace")]
+
+use anyhow::{anyhow, BacktracePolicy, Context, Error};
+use std::io;
+
+fn captured(error: &Error) -> bool {
+    error.backtrace_frames().len() > 0
+}
+
+#[test]
+fn test_scoped() {
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Always, || anyhow!("oh no!"));
+    assert!(captured(&error));
+
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || anyhow!("oh no!"));
+    assert!(!captured(&error));
+}
+
+#[test]
+fn test_every_construction_path() {
+    anyhow::with_backtrace_policy(BacktracePolicy::Always, || {
+        let io_error = || io::Error::new(io::ErrorKind::Other, "oh no!");
+        assert!(captured(&Error::msg("oh no!")));
+        assert!(captured(&Error::new(io_error())));
+        assert!(captured(&Error::from(io_error())));
+        assert!(captured(&Err::<(), _>(io_error()).context("f failed").unwrap_err()));
+        assert!(captured(&None::<()>.context("f failed").unwrap_err()));
+    });
+}
+
+#[test]
+fn test_nested() {
+    anyhow::with_backtrace_policy(BacktracePolicy::Always, || {
+        anyhow::with_backtrace_policy(BacktracePolicy::Never, || {
+            assert!(!captured(&anyhow!("oh no!")));
+        });
+        assert!(captured(&anyhow!("oh no!")));
+    });
+}
+
+#[test]
+fn test_sampled() {
+    let count = anyhow::with_backtrace_policy(BacktracePolicy::Sampled(3), || {
+        (0..30).filter(|_| captured(&anyhow!("oh no!"))).count()
+    });
+    assert_eq!(count, 10);
+
+    anyhow::with_backtrace_policy(BacktracePolicy::Sampled(0), || {
+        assert!(!captured(&anyhow!("oh no!")));
+    });
+}
+
+#[test]
+fn test_global() {
+    anyhow::set_backtrace_policy(BacktracePolicy::Always);
+    let error = std::thread::spawn(|| anyhow!("oh no!")).join().unwrap();
+    assert!(captured(&error));
+
+    anyhow::set_backtrace_policy(BacktracePolicy::Never);
+    let error = std::thread::spawn(|| anyhow!("oh no!")).join().unwrap();
+    assert!(!captured(&error));
+
+    // The scoped policy wins.
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Always, || anyhow!("oh no!"));
+    assert!(captured(&error));
+
+    anyhow::set_backtrace_policy(BacktracePolicy::Sampled(1));
+    let error = std::thread::spawn(|| anyhow!("oh no!")).join().unwrap();
+    assert!(captured(&error));
+
+    anyhow::set_backtrace_policy(BacktracePolicy::Sampled(0));
+    let error = std::thread::spawn(|| anyhow!("oh no!")).join().unwrap();
+    assert!(!captured(&error));
+
+    anyhow::set_backtrace_policy(BacktracePolicy::Env);
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "color")]
+
+use anyhow::{anyhow, Context, Result};
+
+fn f() -> Result<()> {
+    Err(anyhow!("oh no!")).context("f failed").context("g failed")
+}
+
+// Color is decided once per process, so everything is checked from one test.
+#[test]
+fn test_color() {
+    std::env::remove_var("NO_COLOR");
+    std::env::set_var("CLICOLOR_FORCE", "1");
+
+    let debug = format!("{:?}", f().unwrap_err());
+    let expected = "\
+        \x1b[1;31mg failed\x1b[0m\n\
+        \n\
+        Caused by:\n\
+        \x1b[33m    0:\x1b[0m f failed\n\
+        \x1b[33m    1:\x1b[0m oh no!";
+    assert!(debug.starts_with(expected), "{:?}", debug);
+
+    // Display is never styled.
+    assert_eq!("g failed", f().unwrap_err().to_string());
+
+    #[cfg(feature = "backtrace")]
+    {
+        std::env::set_var("RUST_LIB_BACKTRACE", "1");
+        let debug = format!("{:?}", f().unwrap_err());
+        assert!(debug.contains("\x1b[1;32m"), "{}", debug);
+        assert!(debug.contains("\x1b[2m"), "{}", debug);
+    }
+}
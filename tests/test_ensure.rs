@@ -9,6 +9,7 @@
     clippy::let_and_return,
     clippy::let_underscore_untyped,
     clippy::match_bool,
+    clippy::needless_else,
     clippy::never_loop,
     clippy::overly_complex_bool_expr,
     clippy::redundant_closure_call,
@@ -17,11 +18,13 @@
     clippy::unit_arg,
     clippy::while_immutable_condition,
     clippy::zero_ptr,
-    irrefutable_let_patterns
+    irrefutable_let_patterns,
+    missing_abi
 )]
 
 use self::Enum::Generic;
-use anyhow::{anyhow, ensure, Chain, Error, Result};
+use anyhow::{anyhow, ensure, Chain, ConditionFailed, Error, Result};
+use std::cell::Cell;
 use std::fmt::{self, Debug};
 use std::iter;
 use std::marker::{PhantomData, PhantomData as P};
@@ -119,7 +122,10 @@ fn test_low_precedence_control_flow() {
 fn test_low_precedence_binary_operator() {
     // Must not partition as `false == (true && false)`.
     let test = || Ok(ensure!(false == true && false));
-    assert_err(test, "Condition failed: `false == true && false`");
+    assert_err(
+        test,
+        "Condition failed: `false == true && false`: `false == true` (false vs true)",
+    );
 
     // But outside the root level, it is fine.
     let test = || Ok(ensure!(while false == true && false {} < ()));
@@ -462,6 +468,7 @@ fn test_trailer() {
 #[test]
 fn test_whitespace() {
     #[derive(Debug)]
+    #[allow(dead_code)]
     pub struct Point {
         pub x: i32,
         pub y: i32,
@@ -718,4 +725,270 @@ fn test_pat() {
         test,
         "Condition failed: `if let stringify!(x) = \"x\" { 0 } else { 1 } == 1` (0 vs 1)",
     );
-}
\ No newline at end of file
+}
+
+fn without_spaces(expression: &str) -> String {
+    expression.split_whitespace().collect()
+}
+
+#[test]
+fn test_condition_failed() {
+    let x = 1i32;
+    let (error, line) = ((|| Ok(ensure!(x + 1 == 3)))().unwrap_err(), line!());
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), Some("=="));
+    assert_eq!(condition.lhs(), Some("2"));
+    assert_eq!(condition.rhs(), Some("3"));
+    assert_eq!(condition.file(), file!());
+    assert_eq!(condition.line(), line);
+    assert_eq!(error.to_string(), condition.to_string());
+    // Older toolchains stringify the expression with different spacing.
+    assert_eq!(without_spaces(condition.expression()), "x+1==3");
+    if rustversion::cfg!(nightly) {
+        assert_eq!(condition.expression(), "x + 1 == 3");
+    }
+
+    // Too long to be part of the message, but still available.
+    let s = "a string with spaces";
+    let error = (|| Ok(ensure!(s == "")))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.lhs(), Some("\"a string with spaces\""));
+    assert!(!error.to_string().contains(" vs "));
+
+    // Not a comparison.
+    let error = (|| Ok(ensure!(x.is_positive() && false)))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), None);
+    assert_eq!(condition.lhs(), None);
+    assert_eq!(
+        without_spaces(condition.expression()),
+        "x.is_positive()&&false",
+    );
+    let clauses: Vec<_> = condition.clauses().iter().map(|c| c.expression()).collect();
+    assert_eq!(clauses, ["false"]);
+
+    let error = (|| Ok(ensure!(x < 0 || x > 1)))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    let clauses: Vec<_> = condition
+        .clauses()
+        .iter()
+        .map(|c| (without_spaces(c.expression()), c.lhs(), c.rhs()))
+        .collect();
+    assert_eq!(
+        clauses,
+        [
+            ("x<0".to_owned(), Some("1"), Some("0")),
+            ("x>1".to_owned(), Some("1"), Some("1")),
+        ],
+    );
+
+    // A message of its own replaces the ConditionFailed.
+    let error = (|| Ok(ensure!(x == 2, "x must be 2")))().unwrap_err();
+    assert!(error.downcast_ref::<ConditionFailed>().is_none());
+}
+
+#[test]
+fn test_condition_failed_source() {
+    let x = 1i32;
+    let error = (|| Ok(ensure!(x == 2)))().unwrap_err();
+    assert!(error.root_cause().is::<ConditionFailed>());
+    assert!(error.source().is_none());
+
+    let error = error.context("context");
+    let chain: Vec<_> = error.chain().collect();
+    assert_eq!(chain.len(), 2);
+    assert!(chain[1].is::<ConditionFailed>());
+}
+
+#[test]
+fn test_condition_failed_string() {
+    let x = 1i32;
+    let mut error = (|| Ok(ensure!(x == 2)))().unwrap_err();
+    assert_eq!(
+        "Condition failed: `x == 2` (1 vs 2)",
+        error.downcast_ref::<String>().unwrap(),
+    );
+
+    error.downcast_mut::<String>().unwrap().push('!');
+    assert_eq!("Condition failed: `x == 2` (1 vs 2)!", error.to_string());
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!("x == 2", condition.expression());
+
+    let string = error.downcast::<String>().unwrap();
+    assert_eq!("Condition failed: `x == 2` (1 vs 2)!", string);
+
+    let error = (|| Ok(ensure!(x == 2 || x > 1)))().unwrap_err();
+    let condition = error.downcast::<ConditionFailed>().unwrap();
+    let clause = &condition.clauses()[1];
+    assert_eq!("Condition failed: `x > 1` (1 vs 1)", clause.to_string());
+}
+
+#[test]
+fn test_huge_operand() {
+    struct Huge(Cell<usize>);
+
+    impl Debug for Huge {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            for _ in 0..1_000_000 {
+                self.0.set(self.0.get() + 1);
+                f.write_str("x")?;
+            }
+            Ok(())
+        }
+    }
+
+    impl PartialEq for Huge {
+        fn eq(&self, _other: &Self) -> bool {
+            false
+        }
+    }
+
+    let huge = Huge(Cell::new(0));
+    let error = (|| Ok(ensure!(huge == Huge(Cell::new(0)))))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    let lhs = condition.lhs().unwrap();
+    assert!(lhs.len() < 2048, "{}", lhs.len());
+    assert!(lhs.ends_with('…'));
+    assert!(huge.0.get() < 2048, "{}", huge.0.get());
+}
+
+#[test]
+fn test_verbose() {
+    let s = "a string with spaces";
+    let error = (|| Ok(ensure!(verbose: s == "")))().unwrap_err();
+    let expected = "Condition failed: `s == \"\"`\n  left: \"a string with spaces\"\n right: \"\"";
+    assert_eq!(error.to_string(), expected);
+
+    let x = 1i32;
+    let error = (|| Ok(ensure!(verbose: x.is_positive() && false)))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), None);
+
+    let error = (|| Ok(ensure!(verbose: x == 2, "x must be 2")))().unwrap_err();
+    assert_eq!(error.to_string(), "x must be 2");
+
+    let actual = vec!["a", "b", "c"];
+    let expected = vec!["a", "c", "d"];
+    let error = (|| Ok(ensure!(verbose: actual == expected)))().unwrap_err();
+    let expected = [
+        "Condition failed: `actual == expected`",
+        "  diff (- left, + right):",
+        "      [",
+        "          \"a\",",
+        "    -     \"b\",",
+        "          \"c\",",
+        "    +     \"d\",",
+        "      ]",
+    ];
+    assert_eq!(error.to_string(), expected.join("\n"));
+
+    let (n, empty) = (2, false);
+    let error = (|| Ok(ensure!(verbose: n == 3 && empty)))().unwrap_err();
+    let expected = "Condition failed: `n == 3 && empty`\n  `n == 3`\n    left: 2\n   right: 3";
+    assert_eq!(error.to_string(), expected);
+
+    anyhow::set_ensure_operand_limit(8);
+    let long = "é".repeat(10);
+    let error = (|| Ok(ensure!(verbose: long == "")))().unwrap_err();
+    anyhow::set_ensure_operand_limit(1024);
+    let expected = "Condition failed: `long == \"\"`\n  left: \"ééé…\n right: \"\"";
+    assert_eq!(error.to_string(), expected);
+}
+
+#[test]
+fn test_compound() {
+    let (n, empty, none) = (2, false, None::<i32>);
+
+    let test = || Ok(ensure!(n == 3 && empty));
+    assert_err(
+        test,
+        "Condition failed: `n == 3 && empty`: `n == 3` (2 vs 3)",
+    );
+
+    let error = (|| Ok(ensure!(n == 2 && empty)))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), None);
+    assert_eq!(condition.clauses().len(), 1);
+    assert_eq!(condition.clauses()[0].expression(), "empty");
+    assert_eq!(condition.clauses()[0].operator(), None);
+    assert_eq!(
+        error.to_string(),
+        "Condition failed: `n == 2 && empty`: `empty`"
+    );
+
+    // Every alternative of a || is false.
+    let error = (|| Ok(ensure!(n > 2 || empty && n == 2 || none.is_some())))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    let clauses = condition.clauses();
+    assert_eq!(clauses.len(), 3);
+    assert_eq!(clauses[0].operator(), Some(">"));
+    assert_eq!(clauses[0].lhs(), Some("2"));
+    assert_eq!(clauses[0].rhs(), Some("2"));
+    assert_eq!(clauses[1].expression(), "empty");
+    assert_eq!(clauses[2].operator(), None);
+
+    // Short-circuits like it would outside of ensure!.
+    let mut calls = 0;
+    let mut call = || {
+        calls += 1;
+        true
+    };
+    (|| Ok(ensure!(call() || call())))().unwrap();
+    assert_eq!(calls, 1);
+
+    (|| Ok(ensure!(n == 2 && !empty || none.unwrap() == 0)))().unwrap();
+}
+
+#[test]
+fn test_matches() {
+    let none = None::<i32>;
+    let test = || Ok(ensure!(matches!(none, Some(_))));
+    assert_err(
+        test,
+        "Condition failed: `matches!(none, Some(_))` (found None)",
+    );
+
+    let error = (|| Ok(ensure!(matches!(none, Some(1) | Some(2)))))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), None);
+    assert_eq!(condition.lhs(), Some("None"));
+    assert_eq!(condition.rhs(), None);
+
+    // The value is not moved unless the pattern binds it.
+    let s = Some(String::from("s"));
+    let error = (|| Ok(ensure!(matches!(s, Some(ref s) if s.is_empty()))))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.lhs(), Some("Some(\"s\")"));
+    assert_eq!(s.as_deref(), Some("s"));
+
+    let test = || Ok(ensure!(none.is_none() && matches!(none, Some(_))));
+    assert_err(
+        test,
+        "Condition failed: `none.is_none() && matches!(none, Some(_))`: `matches!(none, Some(_))` (found None)",
+    );
+}
+
+#[test]
+fn test_not() {
+    let x = 1;
+    let test = || Ok(ensure!(!(x == 1)));
+    assert_err(test, "Condition failed: `!(x == 1)` (1 vs 1)");
+
+    let error = (|| Ok(ensure!(!(x < 2))))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), Some("<"));
+    assert_eq!(condition.lhs(), Some("1"));
+    assert_eq!(condition.rhs(), Some("2"));
+
+    let test = || Ok(ensure!(x == 1 && !(x != 2)));
+    assert_err(
+        test,
+        "Condition failed: `x == 1 && !(x != 2)`: `!(x != 2)` (1 vs 2)",
+    );
+
+    // Only a single comparison is explained under the negation.
+    let error = (|| Ok(ensure!(!(x == 1 || x == 2))))().unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.operator(), None);
+    assert_eq!(condition.lhs(), None);
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use anyhow::{anyhow, bail, ensure, Context, Error, Result};
+use std::io;
+
+fn not_found(path: &str) -> Result<()> {
+    bail!("no such file: {}", path)
+}
+
+fn load(path: &str) -> Result<()> {
+    not_found(path).with_context(|| format!("failed to load {}", path))
+}
+
+fn check(n: i32) -> Result<()> {
+    ensure!(n > 0);
+    Ok(())
+}
+
+fn check_other(n: i32) -> Result<()> {
+    ensure!(n > 1);
+    Ok(())
+}
+
+#[test]
+fn test_interpolated_values() {
+    let a = not_found("a.toml").unwrap_err();
+    let b = not_found("b.toml").unwrap_err();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+
+    let path = "a.toml";
+    let a = anyhow!("no such file: {path}");
+    let path = "b.toml";
+    let b = anyhow!("no such file: {path}");
+    assert_eq!(a.fingerprint(), b.fingerprint());
+}
+
+#[test]
+fn test_different_messages() {
+    let a = anyhow!("no such file: {}", "a.toml");
+    let b = anyhow!("permission denied: {}", "a.toml");
+    assert_ne!(a.fingerprint(), b.fingerprint());
+
+    let a = anyhow!("no such file");
+    let b = anyhow!("permission denied");
+    assert_ne!(a.fingerprint(), b.fingerprint());
+}
+
+#[test]
+fn test_context() {
+    let a = not_found("a.toml").context("failed to load").unwrap_err();
+    let b = not_found("b.toml").context("failed to load").unwrap_err();
+    let c = not_found("b.toml").context("failed to save").unwrap_err();
+    let d = not_found("b.toml").unwrap_err();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_ne!(a.fingerprint(), c.fingerprint());
+    assert_ne!(a.fingerprint(), d.fingerprint());
+
+    // A formatted context only contributes its type.
+    let a = load("a.toml").unwrap_err();
+    let b = load("b.toml").unwrap_err();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+}
+
+#[test]
+fn test_error_type() {
+    let a = Error::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let b = Error::new(io::Error::new(io::ErrorKind::NotFound, "oh no!"));
+    let c = anyhow!("oh no!");
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_ne!(a.fingerprint(), c.fingerprint());
+}
+
+#[test]
+fn test_ensure() {
+    let a = check(0).unwrap_err();
+    let b = check(-1).unwrap_err();
+    let c = check_other(0).unwrap_err();
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_ne!(a.fingerprint(), c.fingerprint());
+}
+
+#[test]
+fn test_stable() {
+    // The value must not change between runs, builds or releases.
+    let error = anyhow!("oh no!").context("failed to load");
+    assert_eq!(0x2110_e648_02ef_6500, error.fingerprint());
+}
+
+#[test]
+fn test_downcast() {
+    let error = anyhow!("no such file: {}", "a.toml");
+    assert_eq!(
+        "no such file: a.toml",
+        error.downcast_ref::<String>().unwrap()
+    );
+}
+
+#[cfg(feature = "backtrace")]
+mod frames {
+    use crate::common::make_error;
+    use anyhow::Error;
+
+    #[inline(never)]
+    fn first_caller() -> Error {
+        make_error()
+    }
+
+    #[inline(never)]
+    fn second_caller() -> Error {
+        make_error()
+    }
+
+    #[test]
+    fn test_frames() {
+        let a = first_caller();
+        let b = second_caller();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_eq!(a.fingerprint_with_frames(1), b.fingerprint_with_frames(1));
+        assert_ne!(a.fingerprint_with_frames(3), b.fingerprint_with_frames(3));
+        assert_ne!(a.fingerprint(), a.fingerprint_with_frames(3));
+        assert_eq!(
+            a.fingerprint_with_frames(3),
+            first_caller().fingerprint_with_frames(3)
+        );
+        assert_eq!(a.fingerprint(), a.fingerprint_with_frames(0));
+    }
+}
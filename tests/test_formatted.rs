@@ -0,0 +1,201 @@
+use anyhow::{anyhow, bail, ensure, Context, Error, FormattedMessage, Result};
+use std::fmt::{self, Display};
+use std::path::Path;
+
+struct DisplayOnly;
+
+impl Display for DisplayOnly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("display only")
+    }
+}
+
+fn open(path: &str) -> Result<()> {
+    bail!("failed to open {}", path)
+}
+
+fn check(len: usize) -> Result<()> {
+    ensure!(len < 16, "length {} exceeds {max}", len, max = 16);
+    Ok(())
+}
+
+#[test]
+fn test_positional() {
+    let error = anyhow!("{} of {}", 1, "two");
+    assert_eq!("1 of two", error.to_string());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!("{} of {}", message.template());
+    assert_eq!(["1", "\"two\""], message.args());
+    assert_eq!("1 of two", message.message());
+    assert_eq!(0, message.named_args().count());
+}
+
+#[test]
+fn test_named() {
+    let error = anyhow!("{code}: {reason}", code = 404, reason = "not found");
+    assert_eq!("404: not found", error.to_string());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert!(message.args().is_empty());
+    assert_eq!(Some("404"), message.named_arg("code"));
+    assert_eq!(Some("\"not found\""), message.named_arg("reason"));
+    assert_eq!(None, message.named_arg("other"));
+    let named: Vec<_> = message.named_args().collect();
+    assert_eq!(vec![("code", "404"), ("reason", "\"not found\"")], named);
+}
+
+#[test]
+fn test_display_only() {
+    let path = Path::new("/etc/app.toml");
+    let error = anyhow!("{} at {:?}", DisplayOnly, path);
+    assert_eq!("display only at \"/etc/app.toml\"", error.to_string());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(["display only", "\"/etc/app.toml\""], message.args());
+}
+
+struct Hex(u32);
+
+impl fmt::LowerHex for Hex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+#[test]
+fn test_unformattable() {
+    let error = anyhow!("{:x} {:p} {hex:x}", Hex(255), &0, hex = Hex(16));
+    assert!(error.to_string().starts_with("ff 0x"));
+    assert!(error.to_string().ends_with(" 10"));
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!("<unformattable>", message.args()[0]);
+    assert_eq!(Some("<unformattable>"), message.named_arg("hex"));
+}
+
+#[test]
+fn test_format_spec() {
+    let error = anyhow!("{:>5}|{:#x}|{0}", 42, 255);
+    assert_eq!("   42|0xff|42", error.to_string());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(["42", "255"], message.args());
+}
+
+#[test]
+fn test_evaluated_once() {
+    let mut count = 0;
+    let mut next = || {
+        count += 1;
+        count
+    };
+    let error = anyhow!("{} {}", next(), next());
+    assert_eq!("1 2", error.to_string());
+    assert_eq!(2, count);
+}
+
+#[test]
+fn test_implicit_capture() {
+    let path = "/etc/app.toml";
+    let error = anyhow!("failed to open {path}");
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!("failed to open {path}", message.template());
+    assert!(message.args().is_empty());
+    assert_eq!(Some("/etc/app.toml"), message.named_arg("path"));
+
+    let (code, reason) = (404, "not found");
+    let error = anyhow!("{{{code}}} {reason:?} for {path}, {code}", code = code + 1);
+    assert_eq!(
+        "{405} \"not found\" for /etc/app.toml, 405",
+        error.to_string(),
+    );
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    let named: Vec<_> = message.named_args().collect();
+    assert_eq!(
+        vec![
+            ("code", "405"),
+            ("reason", "\"not found\""),
+            ("path", "/etc/app.toml"),
+        ],
+        named,
+    );
+}
+
+#[test]
+fn test_implicit_capture_ambiguous() {
+    // Adjacent placeholders cannot be told apart.
+    let (a, b) = ("x", "y");
+    let error = anyhow!("{a}{b}");
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(0, message.named_args().count());
+
+    // Nor can a placeholder be if the text after it occurs in its value.
+    let (a, b) = ("x: y", "z");
+    let error = anyhow!("{a}: {b}");
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(0, message.named_args().count());
+
+    let (a, b) = ("x", "z");
+    let error = anyhow!("{a}: {b}");
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(Some("x"), message.named_arg("a"));
+    assert_eq!(Some("z"), message.named_arg("b"));
+}
+
+#[test]
+fn test_truncated() {
+    let long = "x".repeat(1000);
+    let error = anyhow!("{} {long}", long);
+    assert_eq!(2001, error.to_string().len());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    let arg = &message.args()[0];
+    assert!(arg.starts_with("\"xxx"));
+    assert!(arg.ends_with('…'));
+    assert_eq!(256 + '…'.len_utf8(), arg.len());
+    assert_eq!(
+        256 + '…'.len_utf8(),
+        message.named_arg("long").unwrap().len(),
+    );
+}
+
+#[test]
+fn test_bail_ensure() {
+    let error = open("/etc/app.toml").unwrap_err();
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!("failed to open {}", message.template());
+
+    let error = check(20).unwrap_err();
+    assert_eq!("length 20 exceeds 16", error.to_string());
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!("length {} exceeds {max}", message.template());
+    assert_eq!(["20"], message.args());
+    assert_eq!(Some("16"), message.named_arg("max"));
+}
+
+#[test]
+fn test_string() {
+    let error = anyhow!("{} of {}", 1, 2);
+    assert_eq!("1 of 2", error.downcast_ref::<String>().unwrap());
+
+    let mut error = error.context("context");
+    error.downcast_mut::<String>().unwrap().push('!');
+    assert_eq!("1 of 2!", error.root_cause().to_string());
+    assert!(error.downcast_ref::<FormattedMessage>().is_some());
+
+    let string = error.downcast::<String>().unwrap();
+    assert_eq!("1 of 2!", string);
+}
+
+#[test]
+fn test_downcast_by_value() {
+    let error = anyhow!("{} of {}", 1, 2);
+    let message = error.downcast::<FormattedMessage>().unwrap();
+    assert_eq!("1 of 2", message.to_string());
+    assert_eq!(["1", "2"], message.args());
+
+    let error: Error = open("/etc/app.toml").context("context").unwrap_err();
+    assert!(error.downcast::<FormattedMessage>().is_ok());
+}
+
+#[test]
+fn test_literal() {
+    let error = anyhow!("oh no!");
+    assert!(error.downcast_ref::<FormattedMessage>().is_none());
+    assert_eq!("oh no!", *error.downcast_ref::<&str>().unwrap());
+}
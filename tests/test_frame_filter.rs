@@ -0,0 +1,26 @@
+#![cfg(feature = "backtrace")]
+
+use anyhow::{anyhow, Error};
+
+#[inline(never)]
+fn helper() -> Error {
+    anyhow!("oh no!")
+}
+
+#[test]
+fn test_frame_filter() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    anyhow::add_frame_filter(|function, _file| function.ends_with("::helper"));
+
+    let debug = format!("{:?}", helper());
+    assert!(debug.contains("Stack backtrace:"), "{}", debug);
+    assert!(debug.contains("test_frame_filter::test_frame_filter"), "{}", debug);
+    assert!(debug.contains("frames hidden"), "{}", debug);
+
+    // Error construction at the top.
+    assert!(!debug.contains("anyhow::__private"), "{}", debug);
+    // Registered filter.
+    assert!(!debug.contains("::helper"), "{}", debug);
+    // Thread entry point at the bottom.
+    assert!(!debug.contains("__rust_begin_short_backtrace"), "{}", debug);
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "futures")]
+
+use anyhow::{anyhow, Error, FutureContext, StreamContext};
+use futures::future::{self, FutureExt as _};
+use futures::stream::{self, StreamExt as _};
+use std::io;
+
+fn io_error() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "oh no!")
+}
+
+#[test]
+fn test_future_context() {
+    let future = future::ready(Err::<(), _>(io_error())).context("f failed");
+    let line = line!() - 1;
+    let error = future.now_or_never().unwrap().unwrap_err();
+    assert_eq!("f failed", error.to_string());
+    assert_eq!("oh no!", error.root_cause().to_string());
+    assert!(error.downcast_ref::<io::Error>().is_some());
+    assert_eq!(line, error.location().unwrap().line());
+    assert_eq!(file!(), error.location().unwrap().file());
+
+    let future = future::ready(Ok::<_, io::Error>(1)).context("f failed");
+    assert_eq!(1, future.now_or_never().unwrap().unwrap());
+}
+
+#[test]
+fn test_future_with_context() {
+    let mut called = false;
+    let future = future::ready(Ok::<_, Error>(1)).with_context(|| {
+        called = true;
+        "f failed"
+    });
+    assert_eq!(1, future.now_or_never().unwrap().unwrap());
+    assert!(!called);
+
+    let future = future::ready(Err::<(), _>(anyhow!("oh no!")));
+    let future = future.with_context(|| format!("{} failed", 'f'));
+    let line = line!() - 1;
+    let error = future.now_or_never().unwrap().unwrap_err();
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+    assert_eq!(line, error.location().unwrap().line());
+}
+
+#[test]
+fn test_stream_context() {
+    let stream = stream::iter(vec![Ok(1), Err(io_error()), Ok(2), Err(io_error())]);
+    let items: Vec<_> = stream
+        .context("read failed")
+        .collect()
+        .now_or_never()
+        .unwrap();
+    assert_eq!(4, items.len());
+    assert_eq!(1, *items[0].as_ref().unwrap());
+    assert_eq!(2, *items[2].as_ref().unwrap());
+    for item in &items[1..] {
+        if let Err(error) = item {
+            assert_eq!("read failed: oh no!", format!("{:#}", error));
+        }
+    }
+}
+
+#[test]
+fn test_stream_with_context() {
+    let stream = stream::iter(vec![Err(io_error()), Ok(1), Err(io_error())]);
+    let mut count = 0;
+    let errors: Vec<String> = stream
+        .with_context(|| {
+            count += 1;
+            format!("item {} failed", count)
+        })
+        .filter_map(|item| future::ready(item.err()))
+        .map(|error| error.to_string())
+        .collect()
+        .now_or_never()
+        .unwrap();
+    assert_eq!(["item 1 failed", "item 2 failed"], *errors);
+}
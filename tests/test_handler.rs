@@ -0,0 +1,86 @@
+use anyhow::{anyhow, DefaultHandler, Error, LocalError, ReportHandler};
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
+
+static SEQUENCE: AtomicUsize = AtomicUsize::new(0);
+
+struct Numbered {
+    id: usize,
+    created_from: String,
+}
+
+impl ReportHandler for Numbered {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[{}] ", self.id)?;
+        if f.alternate() {
+            write!(f, "{:#}", error)
+        } else {
+            DefaultHandler.debug(error, f)
+        }
+    }
+}
+
+fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        anyhow::set_report_handler(|error| {
+            Box::new(Numbered {
+                id: SEQUENCE.fetch_add(1, Ordering::Relaxed),
+                created_from: error.to_string(),
+            })
+        })
+        .unwrap();
+    });
+}
+
+#[test]
+fn test_debug() {
+    install();
+    let error = anyhow!("oh no!");
+    let id = error.report_handler().unwrap().downcast_ref::<Numbered>().unwrap().id;
+    assert!(format!("{:?}", error).starts_with(&format!("[{}] oh no!", id)));
+    assert_eq!(format!("[{}] oh no!", id), format!("{:#?}", error));
+}
+
+#[test]
+fn test_state_survives_context() {
+    install();
+    let error = anyhow!("oh no!").context("f failed");
+    let handler = error.report_handler().unwrap();
+    assert!(handler.is::<Numbered>());
+    assert!(!handler.is::<DefaultHandler>());
+
+    let numbered = handler.downcast_ref::<Numbered>().unwrap();
+    assert_eq!("oh no!", numbered.created_from);
+    let expected = format!("[{}] f failed\n\nCaused by:\n    oh no!", numbered.id);
+    assert!(format!("{:?}", error).starts_with(&expected));
+}
+
+#[test]
+fn test_install_twice() {
+    install();
+    let result = anyhow::set_report_handler(|_| Box::new(DefaultHandler));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_local_error() {
+    install();
+    let error = LocalError::new(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    assert!(format!("{:?}", error).starts_with("oh no!"));
+    assert!(format!("{:#?}", error).starts_with("Custom {"));
+
+    let error = LocalError::from(anyhow!("oh no!"))
+        .context(Rc::<str>::from("f failed"))
+        .context(Rc::<str>::from("g failed"));
+    assert!(format!("{:?}", error).starts_with("g failed\n\nCaused by:"));
+
+    // Only the innermost layer, which was an anyhow::Error, is rendered by
+    // the installed handler.
+    let alternate = format!("{:#?}", error);
+    let expected = "Error {\n    context: \"g failed\",\n    source: Error {\n        context: \"f failed\",\n        source: [";
+    assert!(alternate.starts_with(expected), "{}", alternate);
+}
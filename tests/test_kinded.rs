@@ -0,0 +1,99 @@
+use anyhow::{anyhow, bail, ensure, ConditionFailed, Context, Error, Kinded, KindedContext};
+use std::io;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ApiKind {
+    Internal,
+    NotFound,
+    Conflict,
+}
+
+impl Default for ApiKind {
+    fn default() -> Self {
+        ApiKind::Internal
+    }
+}
+
+type Result<T> = std::result::Result<T, Kinded<ApiKind>>;
+
+fn io_error() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "oh no!"))
+}
+
+#[test]
+fn test_kind() {
+    let error = Kinded::new(ApiKind::NotFound, anyhow!("oh no!"));
+    assert_eq!(ApiKind::NotFound, error.kind());
+    assert_eq!("oh no!", error.to_string());
+
+    let error = Kinded::new(ApiKind::Conflict, error.into_error());
+    assert_eq!(ApiKind::Conflict, error.kind());
+}
+
+#[test]
+fn test_context() {
+    fn f() -> Result<()> {
+        bail!(kind: ApiKind::NotFound, "no user {}", 0);
+    }
+
+    let error = f().context("request failed").unwrap_err();
+    assert_eq!(ApiKind::NotFound, error.kind());
+    assert_eq!("request failed: no user 0", format!("{:#}", error));
+
+    let error = f().with_context(|| "request failed").unwrap_err();
+    assert_eq!(ApiKind::NotFound, error.kind());
+    assert_eq!("request failed", error.to_string());
+}
+
+#[test]
+fn test_convert() {
+    fn f() -> Result<()> {
+        io_error()?;
+        Ok(())
+    }
+    let error = f().unwrap_err();
+    assert_eq!(ApiKind::Internal, error.kind());
+    assert!(error.downcast_ref::<io::Error>().is_some());
+
+    fn g() -> Result<()> {
+        io_error().context("g failed")?;
+        Ok(())
+    }
+    let error = g().unwrap_err();
+    assert_eq!(ApiKind::Internal, error.kind());
+    assert_eq!("g failed: oh no!", format!("{:#}", error));
+
+    // The kind survives a round trip through anyhow::Error.
+    let error = Error::from(Kinded::new(ApiKind::Conflict, anyhow!("oh no!")));
+    let error = Kinded::<ApiKind>::from(error.context("outer"));
+    assert_eq!(ApiKind::Conflict, error.kind());
+}
+
+#[test]
+fn test_ensure() {
+    fn f(n: i32) -> Result<()> {
+        ensure!(kind: ApiKind::Conflict, n != 1);
+        ensure!(kind: ApiKind::NotFound, n != 2, "no item {}", n);
+        ensure!(kind: ApiKind::Conflict, n < 3 && n % 2 == 0);
+        Ok(())
+    }
+    assert!(f(0).is_ok());
+
+    let error = f(1).unwrap_err();
+    assert_eq!(ApiKind::Conflict, error.kind());
+    assert_eq!("Condition failed: `n != 1` (1 vs 1)", error.to_string());
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!("n != 1", condition.expression());
+    assert_eq!(Some("1"), condition.lhs());
+
+    let error = f(2).unwrap_err();
+    assert_eq!(ApiKind::NotFound, error.kind());
+    assert_eq!("no item 2", error.to_string());
+
+    let error = f(4).unwrap_err();
+    assert_eq!(ApiKind::Conflict, error.kind());
+    assert_eq!(
+        "Condition failed: `n < 3 && n % 2 == 0`: `n < 3` (4 vs 3)",
+        error.to_string(),
+    );
+}
@@ -0,0 +1,90 @@
+use anyhow::{anyhow, Error, LocalError};
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::io;
+use std::rc::Rc;
+
+#[derive(Debug)]
+struct RcError {
+    drops: Rc<Cell<usize>>,
+}
+
+impl Display for RcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("oh no!")
+    }
+}
+
+impl std::error::Error for RcError {}
+
+impl Drop for RcError {
+    fn drop(&mut self) {
+        self.drops.set(self.drops.get() + 1);
+    }
+}
+
+fn fail(drops: &Rc<Cell<usize>>) -> Result<(), LocalError> {
+    Err(RcError {
+        drops: drops.clone(),
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_context_and_chain() {
+    let drops = Rc::new(Cell::new(0));
+    let error = fail(&drops).unwrap_err().context("f failed");
+    assert_eq!("f failed", error.to_string());
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(["f failed", "oh no!"], *chain);
+    assert!(error.root_cause().is::<RcError>());
+    drop(error);
+    assert_eq!(1, drops.get());
+
+    // Context that is not Send.
+    let context: Rc<str> = Rc::from("f failed");
+    let error = fail(&drops).unwrap_err().context(context);
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+    assert!(error.downcast_ref::<Rc<str>>().is_some());
+    drop(error);
+    assert_eq!(2, drops.get());
+}
+
+#[test]
+fn test_downcast() {
+    let drops = Rc::new(Cell::new(0));
+    let mut error = fail(&drops).unwrap_err().context("f failed");
+    assert!(error.is::<RcError>());
+    assert!(error.is::<&str>());
+    assert!(error.downcast_mut::<RcError>().is_some());
+    let error = error.downcast::<String>().unwrap_err();
+    let inner = error.downcast::<RcError>().unwrap();
+    assert_eq!(0, drops.get());
+    drop(inner);
+    assert_eq!(1, drops.get());
+}
+
+#[test]
+fn test_into_error() {
+    let error = LocalError::from(anyhow!("oh no!").context("f failed"));
+    let error = Error::try_from(error).unwrap();
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+
+    let error = LocalError::new_sendable(io::Error::new(io::ErrorKind::Other, "oh no!"));
+    let error = Error::try_from(error).unwrap();
+    assert!(error.is::<io::Error>());
+
+    let error = LocalError::from(anyhow!("oh no!")).context("f failed");
+    assert!(Error::try_from(error).is_err());
+
+    let drops = Rc::new(Cell::new(0));
+    let error = fail(&drops).unwrap_err().context("f failed");
+    let error = Error::try_from(error).unwrap_err();
+    let sendable = error.to_sendable();
+    assert_eq!("f failed: oh no!", format!("{:#}", sendable));
+    assert!(sendable.downcast_ref::<String>().is_some());
+    drop(error);
+    assert_eq!(1, drops.get());
+}
@@ -0,0 +1,121 @@
+#![allow(clippy::unnecessary_wraps)]
+
+use anyhow::{anyhow, bail, ensure, Context, Error, Result};
+use std::io;
+use std::panic::Location;
+
+fn assert_line(location: Option<&Location>, line: u32) {
+    let location = location.unwrap();
+    assert_eq!(file!(), location.file());
+    assert_eq!(line, location.line());
+}
+
+fn io_error() -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Other, "oh no!"))
+}
+
+#[test]
+fn test_constructors() {
+    let (error, line) = (anyhow!("oh no!"), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (anyhow!("oh {}!", "no"), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (anyhow!(io::Error::new(io::ErrorKind::Other, "oh no!")), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (Error::msg("oh no!"), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (Error::new(io_error().unwrap_err()), line!());
+    assert_line(error.location(), line);
+}
+
+#[test]
+fn test_bail() {
+    fn f() -> Result<()> {
+        bail!("oh no!");
+    }
+    let line = line!() - 2;
+    assert_line(f().unwrap_err().location(), line);
+}
+
+#[test]
+fn test_ensure() {
+    fn f(x: i32) -> Result<()> {
+        ensure!(x == 0);
+        Ok(())
+    }
+    let line = line!() - 3;
+    assert_line(f(1).unwrap_err().location(), line);
+}
+
+#[test]
+fn test_anyhow_of_error_keeps_location() {
+    let (error, line) = (anyhow!("oh no!"), line!());
+    let error = anyhow!(error);
+    assert_line(error.location(), line);
+}
+
+#[test]
+fn test_context() {
+    let (error, line) = (io_error().context("f failed").unwrap_err(), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (Err::<(), _>(error).context("g failed").unwrap_err(), line!());
+    assert_line(error.location(), line);
+
+    let (error, line) = (None::<()>.with_context(|| "h failed").unwrap_err(), line!());
+    assert_line(error.location(), line);
+}
+
+#[test]
+fn test_chain_with_locations() {
+    let (error, line) = (anyhow!("oh no!"), line!());
+    let error = error.context("f failed");
+    let error = io_error()
+        .context(error.to_string())
+        .unwrap_err()
+        .context("g failed");
+
+    let locations: Vec<(String, Option<u32>)> = error
+        .chain()
+        .with_locations()
+        .map(|(cause, location)| (cause.to_string(), location.map(Location::line)))
+        .collect();
+    assert_eq!(
+        vec![
+            ("g failed".to_owned(), Some(line + 5)),
+            ("f failed".to_owned(), Some(line + 3)),
+            ("oh no!".to_owned(), None),
+        ],
+        locations,
+    );
+}
+
+#[test]
+fn test_question_mark() {
+    fn f() -> Result<()> {
+        io_error()?;
+        Ok(())
+    }
+    let line = line!() - 3;
+    assert_line(f().unwrap_err().location(), line);
+}
+
+#[test]
+fn test_debug() {
+    std::env::set_var("ANYHOW_LOCATION", "1");
+
+    let inner = anyhow!("oh no!");
+    let inner_location = inner.location().unwrap();
+    let error = inner.context("f failed");
+    let outer_location = error.location().unwrap();
+
+    let expected = format!(
+        "f failed\n    at {}\n\nCaused by:\n    oh no!\n    at {}",
+        outer_location, inner_location,
+    );
+    assert!(format!("{:?}", error).starts_with(&expected));
+}
@@ -10,9 +10,11 @@
 mod common;
 
 use self::common::*;
-use anyhow::{anyhow, ensure};
+use anyhow::{anyhow, ensure, require, Result};
 use std::cell::Cell;
+use std::collections::HashMap;
 use std::future;
+use std::io;
 
 #[test]
 fn test_messages() {
@@ -78,3 +80,68 @@ fn test_brace_escape() {
     let err = anyhow!("unterminated ${{..}} expression");
     assert_eq!("unterminated ${..} expression", err.to_string());
 }
+
+#[test]
+fn test_require() {
+    let mut map = HashMap::new();
+    map.insert("k", "1");
+
+    let get = |k: &str| -> Result<&str> { Ok(require!(map.get(k))) };
+    assert_eq!(get("k").unwrap(), "1");
+    assert_eq!(get("x").unwrap_err().to_string(), "`map.get(k)` is None");
+
+    let get = |k: &str| -> Result<&str> { Ok(require!(map.get(k), "missing key {}", k)) };
+    assert_eq!(get("x").unwrap_err().to_string(), "missing key x");
+
+    let get = |k: &str| -> Result<&str> {
+        Ok(require!(map.get(k), io::Error::from(io::ErrorKind::NotFound)))
+    };
+    assert!(get("x").unwrap_err().is::<io::Error>());
+
+    // The error of a Result stays around as the source.
+    let parse = |s: &str| -> Result<i32> { Ok(require!(s.parse::<i32>(), "not a number")) };
+    assert_eq!(parse("1").unwrap(), 1);
+    let error = parse("x").unwrap_err();
+    assert_eq!(error.to_string(), "not a number");
+    assert!(error.downcast_ref::<std::num::ParseIntError>().is_some());
+    assert_eq!(error.chain().count(), 2);
+
+    let parse = |s: &str| -> Result<i32> { Ok(require!(s.parse::<i32>())) };
+    let error = parse("x").unwrap_err();
+    assert!(error.is::<std::num::ParseIntError>());
+    assert_eq!(error.chain().count(), 1);
+
+    let inner = || -> Result<i32> { Err(anyhow!("inner")) };
+    let outer = || -> Result<i32> { Ok(require!(inner(), "outer")) };
+    let error = outer().unwrap_err();
+    assert_eq!(format!("{:#}", error), "outer: inner");
+}
+
+#[rustversion::since(1.65)]
+#[test]
+fn test_ensure_let() {
+    use anyhow::{ensure_let, ConditionFailed, Error};
+
+    fn first(v: &[i32]) -> Result<i32> {
+        ensure_let!([x, ..] = v);
+        Ok(*x)
+    }
+    assert_eq!(first(&[1, 2]).unwrap(), 1);
+    let error = first(&[]).unwrap_err();
+    let condition = error.downcast_ref::<ConditionFailed>().unwrap();
+    assert_eq!(condition.expression(), "let [x, ..] = v");
+
+    fn either(pair: (Option<i32>, Option<i32>)) -> Result<i32> {
+        ensure_let!((Some(x), _) | (None, Some(x)) = pair, "no value in {:?}", pair);
+        Ok(x)
+    }
+    assert_eq!(either((None, Some(1))).unwrap(), 1);
+    let error = either((None, None)).unwrap_err();
+    assert_eq!(error.to_string(), "no value in (None, None)");
+
+    fn unwrap_err(result: Result<(), Error>) -> Result<Error> {
+        ensure_let!(Err(error) = result, io::Error::from(io::ErrorKind::Other));
+        Ok(error)
+    }
+    assert!(unwrap_err(Ok(())).unwrap_err().is::<io::Error>());
+}
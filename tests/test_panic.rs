@@ -0,0 +1,87 @@
+use anyhow::{Error, Panic};
+use std::panic;
+use std::thread;
+
+#[test]
+fn test_catch_unwind() {
+    anyhow::install_panic_hook();
+
+    let result = anyhow::catch_unwind(|| 1);
+    assert_eq!(1, result.unwrap());
+
+    let error = anyhow::catch_unwind(|| panic!("oh {}!", "no")).unwrap_err();
+    let line = line!() - 1;
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(Some("oh no!"), panic.message());
+    assert_eq!(Some(file!()), panic.file());
+    assert_eq!(Some(line), panic.line());
+    assert_eq!(Some("test_catch_unwind"), panic.thread());
+    let expected = format!(
+        "thread 'test_catch_unwind' panicked at {}:{}:{}: oh no!",
+        file!(),
+        line,
+        panic.column().unwrap(),
+    );
+    assert_eq!(expected, error.to_string());
+}
+
+#[test]
+fn test_non_string_payload() {
+    let error = anyhow::catch_unwind(|| panic::panic_any(1)).unwrap_err();
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(None, panic.message());
+    assert!(error.to_string().ends_with(": Box<dyn Any>"));
+}
+
+#[test]
+fn test_from_panic() {
+    anyhow::install_panic_hook();
+    let payload = panic::catch_unwind(|| panic!("oh no!")).unwrap_err();
+    let line = line!() - 1;
+    let error = Error::from_panic(payload);
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(Some("oh no!"), panic.message());
+    assert_eq!(Some(line), panic.line());
+}
+
+#[test]
+fn test_join() {
+    anyhow::install_panic_hook();
+
+    let handle = thread::spawn(|| 1);
+    assert_eq!(1, anyhow::join(handle).unwrap());
+
+    let handle = thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| panic!("oh no!"))
+        .unwrap();
+    let line = line!() - 2;
+    let error = anyhow::join::<()>(handle).unwrap_err();
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(Some("oh no!"), panic.message());
+    assert_eq!(Some("worker"), panic.thread());
+    assert_eq!(Some(line), panic.line());
+}
+
+#[test]
+fn test_from_thread_panic() {
+    let handle = thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| panic::panic_any(1))
+        .unwrap();
+    let thread = handle.thread().clone();
+    let payload = handle.join().unwrap_err();
+    let error = Error::from_thread_panic(payload, &thread);
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(Some("worker"), panic.thread());
+
+    let handle = thread::Builder::new()
+        .name("worker".to_owned())
+        .spawn(|| panic::panic_any(1))
+        .unwrap();
+    let payload = handle.join().unwrap_err();
+    let error = Error::from_panic(payload);
+    let panic = error.downcast_ref::<Panic>().unwrap();
+    assert_eq!(None, panic.thread());
+    assert_eq!("panicked: Box<dyn Any>", error.to_string());
+}
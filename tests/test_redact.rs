@@ -0,0 +1,136 @@
+use anyhow::{
+    anyhow, bail, BacktracePolicy, Context, Error, FormattedMessage, Redactor, Result, Sensitive,
+};
+use std::io;
+
+fn login(user: &str, token: &str) -> Result<()> {
+    bail!(
+        "user {} rejected token {:?}",
+        Sensitive(user),
+        Sensitive(token)
+    )
+}
+
+fn debug(error: &Error) -> (String, String) {
+    (format!("{:?}", error), format!("{:?}", error.redacted()))
+}
+
+#[test]
+fn test_format_argument() {
+    let error = login("alice", "hunter2").unwrap_err();
+    assert_eq!("user alice rejected token \"hunter2\"", error.to_string());
+    assert_eq!(
+        "user <redacted> rejected token <redacted>",
+        error.redacted().to_string(),
+    );
+
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(["<redacted>", "<redacted>"], message.args());
+    assert_eq!("user alice rejected token \"hunter2\"", message.message());
+}
+
+#[test]
+fn test_implicit_capture() {
+    let secret = Sensitive("swordfish");
+    let error = anyhow!("password {secret} is wrong");
+    assert_eq!("password swordfish is wrong", error.to_string());
+    assert_eq!("password <redacted> is wrong", error.redacted().to_string());
+
+    let message = error.downcast_ref::<FormattedMessage>().unwrap();
+    assert_eq!(Some("<redacted>"), message.named_arg("secret"));
+}
+
+#[test]
+fn test_context() {
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || {
+        login("alice", "hunter2")
+            .context(Sensitive("while logging in as alice"))
+            .unwrap_err()
+    });
+    assert_eq!(
+        "while logging in as alice: user alice rejected token \"hunter2\"",
+        format!("{:#}", error),
+    );
+    assert_eq!(
+        "<redacted>: user <redacted> rejected token <redacted>",
+        format!("{:#}", error.redacted()),
+    );
+
+    let (clear, redacted) = debug(&error);
+    assert!(clear.contains("hunter2"), "{}", clear);
+    assert_eq!(
+        "<redacted>\n\nCaused by:\n    user <redacted> rejected token <redacted>",
+        redacted,
+    );
+}
+
+#[test]
+fn test_message() {
+    let error = Error::msg(Sensitive("clear text"));
+    assert_eq!("clear text", error.to_string());
+    assert_eq!("<redacted>", error.redacted().to_string());
+    assert_eq!("clear text", error.to_string());
+}
+
+#[test]
+fn test_padding() {
+    let error = anyhow!("[{:>12}]", Sensitive(7));
+    assert_eq!("[           7]", error.to_string());
+    assert_eq!("[<redacted>]", error.redacted().to_string());
+
+    let error = Error::msg(format!("[{:>12}]", Sensitive(7)));
+    assert_eq!("[           7]", error.redacted().to_string());
+}
+
+#[test]
+fn test_redactors() {
+    anyhow::add_redactor(Redactor::literal("hunter3"));
+    anyhow::add_redactor(Redactor::after("session="));
+    anyhow::add_redactor(Redactor::word(|word| word.ends_with("@example.org")));
+
+    let error = Error::new(io::Error::new(
+        io::ErrorKind::PermissionDenied,
+        "bob@example.org used hunter3 (session=1f2e3d), denied",
+    ))
+    .context("request failed for bob@example.org.");
+    assert_eq!(
+        "request failed for <redacted>.: <redacted> used <redacted> (session=<redacted>), denied",
+        format!("{:#}", error.redacted()),
+    );
+    assert_eq!(
+        "request failed for bob@example.org.: bob@example.org used hunter3 (session=1f2e3d), denied",
+        format!("{:#}", error),
+    );
+
+    let (_clear, redacted) = debug(&error);
+    assert!(!redacted.contains("hunter3"), "{}", redacted);
+    assert!(!redacted.contains("1f2e3d"), "{}", redacted);
+
+    let error = anyhow!("session={} hunter3", Sensitive("x"));
+    assert_eq!(
+        "session=<redacted> <redacted>",
+        error.redacted().to_string()
+    );
+}
+
+#[test]
+fn test_modified_message() {
+    let mut error = anyhow!("token {} rejected", Sensitive("hunter2"));
+    error.downcast_mut::<String>().unwrap().truncate(3);
+    assert_eq!("tok", error.redacted().to_string());
+
+    let mut error = anyhow!("token {} rejected", Sensitive("hunter2"));
+    let message = error.downcast_mut::<String>().unwrap();
+    message.clear();
+    message.push_str("token hunter\u{e9}!");
+    assert_eq!("token <redacted>!", error.redacted().to_string());
+}
+
+#[test]
+fn test_alternate_debug() {
+    let error = login("carol", "hunter4").unwrap_err();
+    let redacted = format!("{:#?}", error.redacted());
+    assert!(redacted.contains("template"), "{}", redacted);
+    assert!(!redacted.contains("hunter4"), "{}", redacted);
+    assert!(!redacted.contains("carol"), "{}", redacted);
+}
@@ -0,0 +1,190 @@
+#![cfg(feature = "serde")]
+
+use anyhow::{anyhow, Context, Error, Report};
+use serde_json::json;
+use std::io;
+
+fn error() -> Error {
+    let error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    Err::<(), _>(error)
+        .context("f failed")
+        .context("g failed".to_owned())
+        .unwrap_err()
+}
+
+#[test]
+fn test_layers() {
+    let report = Report::new(&error());
+    assert_eq!("g failed", report.message());
+
+    let layers: Vec<(&str, Option<&str>)> = report
+        .layers()
+        .iter()
+        .map(|layer| (layer.display(), layer.type_name()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("g failed", Some("alloc::string::String")),
+            ("f failed", Some("&str")),
+            ("oh no!", Some(std::any::type_name::<io::Error>())),
+        ],
+        layers,
+    );
+    assert_eq!(format!("{:?}", error().root_cause()), report.layers()[2].debug());
+}
+
+#[test]
+fn test_unknown_type_name() {
+    let error = anyhow!(Box::<dyn std::error::Error + Send + Sync>::from("oh no!"));
+    let report = Report::new(&error);
+    assert_eq!(None, report.layers()[0].type_name());
+}
+
+#[test]
+fn test_serialize() {
+    let error = anyhow!("oh no!").context("f failed");
+    let value = serde_json::to_value(&error).unwrap();
+    let debug = format!("{:?}", error.chain().next().unwrap());
+    assert_eq!(json!("f failed"), value["message"]);
+    assert_eq!(
+        json!([
+            { "display": "f failed", "debug": debug, "type_name": "&str" },
+            { "display": "oh no!", "debug": "\"oh no!\"", "type_name": "&str" },
+        ]),
+        value["chain"],
+    );
+    assert!(value["backtrace"].is_array());
+}
+
+#[test]
+fn test_round_trip() {
+    let original = error();
+    let json = serde_json::to_string(&original).unwrap();
+    let error: Error = serde_json::from_str(&json).unwrap();
+
+    assert!(error.is_remote());
+    assert!(!original.is_remote());
+    assert!(!error.is::<io::Error>());
+    assert_eq!(format!("{:#}", original), format!("{:#}", error));
+
+    let original_chain: Vec<String> = original.chain().map(ToString::to_string).collect();
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(original_chain, chain);
+
+    let expected = Report::new(&original);
+    let report = Report::new(&error);
+    for (expected, layer) in expected.layers().iter().zip(report.layers()) {
+        assert_eq!(expected.debug(), layer.debug());
+        assert_eq!(expected.type_name(), layer.type_name());
+    }
+}
+
+#[test]
+fn test_context_on_remote() {
+    let json = serde_json::to_string(&anyhow!("oh no!")).unwrap();
+    let error: Error = serde_json::from_str(&json).unwrap();
+    let error = error.context("f failed");
+    assert!(error.is_remote());
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+}
+
+#[test]
+fn test_long_chain() {
+    let chain: Vec<_> = (0..200_000)
+        .map(|n| json!({ "display": n.to_string() }))
+        .collect();
+    let json = json!({ "message": "0", "chain": chain });
+    let error: Error = serde_json::from_value(json).unwrap();
+    assert_eq!(200_000, error.chain().count());
+    assert_eq!("199999", error.root_cause().to_string());
+
+    let report = Report::new(&error);
+    assert_eq!(200_000, report.layers().len());
+    assert_eq!("199999", report.layers()[199_999].display());
+    drop(error);
+}
+
+#[test]
+fn test_remote_backtrace() {
+    let json = json!({
+        "message": "oh no!",
+        "chain": [{ "display": "oh no!", "debug": "\"oh no!\"", "type_name": null }],
+        "backtrace": [
+            { "function": "worker::run", "file": "./src/worker.rs", "line": 12, "column": 5 },
+            { "function": null, "file": null, "line": null, "column": null },
+        ],
+    });
+    let error: Error = serde_json::from_value(json).unwrap();
+    let backtrace = error.remote_backtrace().unwrap();
+    assert_eq!(2, backtrace.frames().len());
+    assert_eq!(Some("worker::run"), backtrace.frames()[0].function());
+    assert_eq!(
+        "   0: worker::run\n             at ./src/worker.rs:12:5\n   1: <unknown>",
+        backtrace.to_string(),
+    );
+
+    let error: Error = serde_json::from_value(json!({ "message": "oh no!" })).unwrap();
+    assert!(error.remote_backtrace().is_none());
+    assert_eq!("oh no!", error.to_string());
+}
+
+#[test]
+fn test_duplicate_field() {
+    let json = r#"{"message": "a", "message": "b"}"#;
+    let error = serde_json::from_str::<Report>(json).unwrap_err();
+    assert!(
+        error.to_string().starts_with("duplicate field `message`"),
+        "{}",
+        error,
+    );
+
+    let json = r#"{"message": "a", "chain": [{"display": "a", "debug": "x", "debug": "y"}]}"#;
+    let error = serde_json::from_str::<Report>(json).unwrap_err();
+    assert!(
+        error.to_string().starts_with("duplicate field `debug`"),
+        "{}",
+        error,
+    );
+
+    let json = r#"{"message": "a", "backtrace": [{"line": 1, "line": 2}]}"#;
+    let error = serde_json::from_str::<Report>(json).unwrap_err();
+    assert!(
+        error.to_string().starts_with("duplicate field `line`"),
+        "{}",
+        error,
+    );
+}
+
+#[test]
+fn test_mismatched_message() {
+    let json = json!({
+        "message": "g failed",
+        "chain": [{ "display": "f failed" }, { "display": "oh no!" }],
+    });
+    let error = serde_json::from_value::<Report>(json).unwrap_err();
+    assert!(error.to_string().contains("\"g failed\""), "{}", error);
+
+    let json = json!(["g failed", [{ "display": "f failed" }]]);
+    assert!(serde_json::from_value::<Report>(json).is_err());
+
+    let json = json!({
+        "message": "f failed",
+        "chain": [{ "display": "f failed" }, { "display": "oh no!" }],
+    });
+    let error: Error = serde_json::from_value(json).unwrap();
+    assert_eq!("f failed: oh no!", format!("{:#}", error));
+}
+
+#[cfg(feature = "backtrace")]
+#[test]
+fn test_backtrace_frames() {
+    std::env::set_var("RUST_LIB_BACKTRACE", "1");
+    let report = Report::new(&anyhow!("oh no!"));
+    let frame = report
+        .frames()
+        .iter()
+        .find(|frame| frame.function().map_or(false, |f| f.contains("test_backtrace_frames")))
+        .unwrap();
+    assert!(frame.file().unwrap().ends_with("test_report.rs"));
+    assert!(frame.line().is_some());
+}
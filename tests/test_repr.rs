@@ -4,7 +4,7 @@
 mod drop;
 
 use self::drop::{DetectDrop, Flag};
-use anyhow::Error;
+use anyhow::{Error, Kinded};
 use std::marker::Unpin;
 use std::mem;
 
@@ -13,6 +13,17 @@ fn test_error_size() {
     assert_eq!(mem::size_of::<Error>(), mem::size_of::<usize>());
 }
 
+#[test]
+fn test_kinded_size() {
+    #[allow(dead_code)]
+    enum Kind {
+        A,
+        B,
+    }
+    assert_eq!(mem::size_of::<Kinded<Kind>>(), mem::size_of::<usize>());
+    assert_eq!(mem::size_of::<Kinded<u64>>(), mem::size_of::<usize>());
+}
+
 #[test]
 fn test_null_pointer_optimization() {
     assert_eq!(mem::size_of::<Result<(), Error>>(), mem::size_of::<usize>());
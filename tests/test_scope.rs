@@ -0,0 +1,59 @@
+use anyhow::{anyhow, scope, BacktracePolicy, Context, Result};
+use std::thread;
+
+fn fail() -> Result<()> {
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || anyhow!("oh no!"));
+    Err(error)
+}
+
+#[test]
+fn test_no_scope() {
+    let error = fail().unwrap_err();
+    assert_eq!("oh no!", format!("{:?}", error));
+}
+
+#[test]
+fn test_nested() {
+    let error = {
+        let _outer = scope!("processing order {}", 1234);
+        let _inner = scope!("reserving item {}", 7);
+        fail().unwrap_err()
+    };
+    let expected = "oh no!\n\nWhile:\n    processing order 1234\n    reserving item 7";
+    assert_eq!(expected, format!("{:?}", error));
+
+    let error = {
+        let _outer = scope!("processing order {}", 1234);
+        {
+            let _inner = scope!("reserving item {}", 7);
+        }
+        fail().unwrap_err()
+    };
+    let expected = "oh no!\n\nWhile:\n    processing order 1234";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_context_outside_scope() {
+    let result = {
+        let _scope = scope!("reserving item 7");
+        fail()
+    };
+    let error = result.context("order failed").unwrap_err();
+    let expected = "\
+        order failed\n\
+        \n\
+        Caused by:\n    \
+            oh no!\n\
+        \n\
+        While:\n    \
+            reserving item 7";
+    assert_eq!(expected, format!("{:?}", error));
+}
+
+#[test]
+fn test_thread_local() {
+    let _scope = scope!("on the main thread");
+    let error = thread::spawn(|| fail().unwrap_err()).join().unwrap();
+    assert_eq!("oh no!", format!("{:?}", error));
+}
@@ -0,0 +1,96 @@
+mod drop;
+
+use crate::drop::{DetectDrop, Flag};
+use anyhow::{anyhow, Context, Error, Result, SharedError};
+use std::io;
+
+fn error() -> Error {
+    let error = io::Error::new(io::ErrorKind::Other, "oh no!");
+    Error::new(error).context("f failed").context("g failed")
+}
+
+fn rethrow(shared: SharedError) -> Result<()> {
+    Err(shared)?;
+    unreachable!()
+}
+
+#[test]
+fn test_autotraits() {
+    fn assert<E: Clone + Send + Sync + std::error::Error + 'static>() {}
+    assert::<SharedError>();
+}
+
+#[test]
+fn test_clone() {
+    let shared = error().into_shared();
+    let clone = shared.clone();
+    assert!(SharedError::ptr_eq(&shared, &clone));
+    assert_eq!("g failed", clone.to_string());
+    assert_eq!("g failed: f failed: oh no!", format!("{:#}", clone));
+}
+
+#[test]
+fn test_reaches_original() {
+    let shared = error().into_shared();
+    assert!(shared.downcast_ref::<io::Error>().is_some());
+    assert_eq!("oh no!", shared.root_cause().to_string());
+    let chain: Vec<String> = shared.chain().map(ToString::to_string).collect();
+    assert_eq!(vec!["g failed", "f failed", "oh no!"], chain);
+}
+
+#[test]
+fn test_convert_back() {
+    let shared = error().into_shared();
+    let error = rethrow(shared.clone()).unwrap_err();
+
+    assert!(error.is::<SharedError>());
+    assert!(error.is::<io::Error>());
+    assert_eq!(Some(&"g failed"), error.downcast_ref::<&str>());
+
+    let chain: Vec<String> = error.chain().map(ToString::to_string).collect();
+    assert_eq!(vec!["g failed", "f failed", "oh no!"], chain);
+    assert_eq!(format!("{:#}", shared), format!("{:#}", error));
+}
+
+#[test]
+fn test_context_on_converted() {
+    let shared = error().into_shared();
+    let error = rethrow(shared).context("h failed").unwrap_err();
+    assert!(error.is::<io::Error>());
+    assert_eq!(
+        "h failed: g failed: f failed: oh no!",
+        format!("{:#}", error),
+    );
+}
+
+#[test]
+fn test_downcast_by_value() {
+    let shared = error().into_shared();
+    let error = rethrow(shared).unwrap_err();
+
+    // The original error is shared, so it cannot be moved out.
+    let error = error.downcast::<io::Error>().unwrap_err();
+    let shared = error.downcast::<SharedError>().unwrap();
+    assert!(shared.is::<io::Error>());
+}
+
+#[test]
+fn test_try_unwrap() {
+    let shared = anyhow!("oh no!").into_shared();
+    let clone = shared.clone();
+    let shared = SharedError::try_unwrap(shared).unwrap_err();
+    drop(clone);
+    let error = SharedError::try_unwrap(shared).unwrap();
+    assert_eq!("oh no!", error.to_string());
+}
+
+#[test]
+fn test_drop() {
+    let has_dropped = Flag::new();
+    let shared = Error::new(DetectDrop::new(&has_dropped)).into_shared();
+    let error = rethrow(shared.clone()).unwrap_err();
+    drop(shared);
+    assert!(!has_dropped.get());
+    drop(error);
+    assert!(has_dropped.get());
+}
@@ -0,0 +1,52 @@
+#![cfg(std_backtrace)]
+
+mod common;
+
+use self::common::make_error;
+use anyhow::{anyhow, BacktracePolicy};
+use std::backtrace::{Backtrace, BacktraceStatus};
+use std::path::Path;
+
+#[test]
+fn test_std_backtrace() {
+    let error = make_error();
+    let backtrace: &Backtrace = error.backtrace();
+    assert_eq!(backtrace.status(), BacktraceStatus::Captured);
+
+    let debug = format!("{:?}", error);
+    assert!(debug.contains("Stack backtrace:"), "{}", debug);
+}
+
+#[test]
+fn test_context_keeps_backtrace() {
+    let error = make_error().context("f failed");
+    assert_eq!(error.backtrace().status(), BacktraceStatus::Captured);
+}
+
+#[test]
+fn test_frames() {
+    let error = make_error();
+    let frames: Vec<_> = error.backtrace_frames().collect();
+
+    let frame = frames
+        .iter()
+        .find(|frame| {
+            frame
+                .name()
+                .map_or(false, |name| name.ends_with("::test_frames"))
+        })
+        .unwrap_or_else(|| panic!("{:#?}", frames));
+    // Not available from the standard library's backtrace.
+    assert_eq!(frame.ip(), None);
+    assert!(frame.line().is_some());
+    assert!(frame.column().is_some());
+    let file = frame.file().unwrap();
+    assert_eq!(file.file_name(), Path::new(file!()).file_name());
+}
+
+#[test]
+fn test_disabled() {
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || anyhow!("oh no!"));
+    assert_eq!(error.backtrace().status(), BacktraceStatus::Disabled);
+    assert_eq!(error.backtrace_frames().len(), 0);
+}
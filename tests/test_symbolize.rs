@@ -0,0 +1,98 @@
+#![cfg(all(feature = "symbolize", target_os = "linux"))]
+
+mod common;
+
+use self::common::make_error;
+use anyhow::{anyhow, BacktracePolicy, RawBacktrace};
+use std::env;
+use std::path::Path;
+
+#[test]
+fn test_modules() {
+    let raw = make_error().raw_backtrace().unwrap();
+    assert!(!raw.frames().is_empty());
+
+    let exe = env::current_exe().unwrap();
+    let module = raw
+        .modules()
+        .iter()
+        .find(|module| module.path() == exe)
+        .unwrap_or_else(|| panic!("{:#?}", raw));
+    let range = module.address_range();
+    assert!(raw.frames().iter().any(|ip| range.contains(ip)));
+    assert!(module.base() <= range.start);
+}
+
+#[test]
+fn test_round_trip() {
+    let raw = make_error().raw_backtrace().unwrap();
+    let dump = raw.to_string();
+    assert!(dump.starts_with("anyhow raw backtrace\n"), "{}", dump);
+    assert_eq!(raw, dump.parse::<RawBacktrace>().unwrap());
+}
+
+#[test]
+fn test_parse_error() {
+    let error = "frame 0x1000".parse::<RawBacktrace>().unwrap_err();
+    assert!(error.to_string().starts_with("not a raw backtrace"));
+
+    let error = "anyhow raw backtrace\nframe zzz"
+        .parse::<RawBacktrace>()
+        .unwrap_err();
+    assert_eq!("invalid frame line: frame zzz", error.to_string());
+}
+
+#[test]
+fn test_malformed_module() {
+    let lines = [
+        "module 0x1000-0x2000 base 0x1000 build-id a\u{e9}0 /x",
+        "module 0x1000-0x2000 base 0x1000 build-id +a /x",
+        "module 0x1000-0x2000 base 0x1000 build-id abc /x",
+        "module 0x1000-0x2000 base 0x1000 build-id \u{e9}\u{e9} /x",
+        "module 0x1000\u{e9}0x2000 base 0x1000 build-id - /x",
+        "module 0x1000-0x2000 base",
+    ];
+    for line in &lines {
+        let dump = format!("anyhow raw backtrace\n{}", line);
+        let error = dump.parse::<RawBacktrace>().unwrap_err();
+        assert_eq!(format!("invalid module line: {}", line), error.to_string());
+    }
+
+    let dump = "anyhow raw backtrace\nmodule 0x1000-0x2000 base 0x1000 build-id 0aFf /x y";
+    let raw = dump.parse::<RawBacktrace>().unwrap();
+    assert_eq!(Some(&[0x0a, 0xff][..]), raw.modules()[0].build_id());
+    assert_eq!(Path::new("/x y"), raw.modules()[0].path());
+}
+
+#[test]
+fn test_resolve() {
+    let raw = make_error().raw_backtrace().unwrap();
+    let frames = raw.resolve(&[env::current_exe().unwrap()]).unwrap();
+
+    let frame = frames
+        .iter()
+        .find(|frame| {
+            frame
+                .name()
+                .map_or(false, |name| name.ends_with("::test_resolve"))
+        })
+        .unwrap_or_else(|| panic!("{:#?}", frames));
+    assert!(raw.frames().contains(&frame.ip().unwrap()));
+    assert!(frame.line().is_some());
+    let file = frame.file().unwrap();
+    assert_eq!(file.file_name(), Path::new(file!()).file_name());
+}
+
+#[test]
+fn test_no_debug_files() {
+    let raw = make_error().raw_backtrace().unwrap();
+    let frames = raw.resolve::<&Path>(&[]).unwrap();
+    assert_eq!(raw.frames().len(), frames.len());
+    assert!(frames.iter().all(|frame| frame.name().is_none()));
+}
+
+#[test]
+fn test_not_captured() {
+    let error = anyhow::with_backtrace_policy(BacktracePolicy::Never, || anyhow!("oh no!"));
+    assert!(error.raw_backtrace().is_none());
+}